@@ -0,0 +1,137 @@
+// src/album_art.rs
+use ratatui::layout::Rect;
+use std::env;
+
+use crate::metadata::CoverArt;
+
+/// Terminal graphics protocols that can show an embedded cover as-is.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    Kitty,
+    /// iTerm2's inline images, also understood by WezTerm.
+    Iterm2,
+}
+
+/// Guesses the image protocol from the environment the terminal sets up.
+pub fn detect_image_protocol() -> Option<ImageProtocol> {
+    let term = env::var("TERM").unwrap_or_default();
+    if env::var_os("KITTY_WINDOW_ID").is_some() || term.contains("kitty") {
+        return Some(ImageProtocol::Kitty);
+    }
+    match env::var("TERM_PROGRAM").ok()?.as_str() {
+        "iTerm.app" | "WezTerm" => Some(ImageProtocol::Iterm2),
+        _ => None,
+    }
+}
+
+/// Whether `protocol` can display this image. Kitty is only handed PNGs,
+/// since decoding anything else would need an image library.
+pub fn can_display(protocol: ImageProtocol, art: &CoverArt) -> bool {
+    match protocol {
+        ImageProtocol::Kitty => art.data.starts_with(b"\x89PNG"),
+        ImageProtocol::Iterm2 => true,
+    }
+}
+
+/// Escape sequence that draws `art` scaled into `area`, to be written with
+/// the cursor at the area's top-left corner.
+pub fn image_escape(protocol: ImageProtocol, art: &CoverArt, area: Rect) -> String {
+    let encoded = base64(&art.data);
+    match protocol {
+        ImageProtocol::Kitty => {
+            // Payloads are sent in chunks of at most 4096 bytes; `m=1` marks
+            // that more follow.
+            let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+            let mut out = String::new();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(i + 1 < chunks.len());
+                let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+                if i == 0 {
+                    out.push_str(&format!(
+                        "\x1b_Ga=T,f=100,q=2,C=1,c={},r={},m={};{}\x1b\\",
+                        area.width, area.height, more, chunk
+                    ));
+                } else {
+                    out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                }
+            }
+            out
+        }
+        ImageProtocol::Iterm2 => format!(
+            "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+            art.data.len(),
+            area.width,
+            area.height,
+            encoded
+        ),
+    }
+}
+
+/// Escape sequence that removes previously drawn images, for protocols
+/// that keep them outside the text grid.
+pub fn clear_escape(protocol: ImageProtocol) -> &'static str {
+    match protocol {
+        ImageProtocol::Kitty => "\x1b_Ga=d,q=2\x1b\\",
+        // iTerm2 images live in the cells and are wiped with the screen.
+        ImageProtocol::Iterm2 => "",
+    }
+}
+
+/// Short description like "JPEG 500×500" for terminals that can't show the
+/// image itself.
+pub fn describe(art: &CoverArt) -> String {
+    let format = if art.data.starts_with(b"\x89PNG") {
+        "PNG"
+    } else if art.data.starts_with(&[0xff, 0xd8]) {
+        "JPEG"
+    } else {
+        art.media_type.strip_prefix("image/").unwrap_or("image")
+    };
+    match image_size(&art.data) {
+        Some((width, height)) => format!("{} {}×{}", format.to_uppercase(), width, height),
+        None => format.to_uppercase(),
+    }
+}
+
+/// Pixel dimensions read from a PNG or JPEG header.
+fn image_size(data: &[u8]) -> Option<(u32, u32)> {
+    if data.starts_with(b"\x89PNG") {
+        let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+        let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+        return Some((width, height));
+    }
+    if !data.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    // Walk the JPEG segments until a start-of-frame marker.
+    let mut pos = 2;
+    loop {
+        let marker = *data.get(pos + 1)?;
+        let length = u16::from_be_bytes(data.get(pos + 2..pos + 4)?.try_into().ok()?) as usize;
+        let is_sof = matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+        if is_sof {
+            let height = u16::from_be_bytes(data.get(pos + 5..pos + 7)?.try_into().ok()?);
+            let width = u16::from_be_bytes(data.get(pos + 7..pos + 9)?.try_into().ok()?);
+            return Some((width as u32, height as u32));
+        }
+        pos += 2 + length;
+    }
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -1,36 +1,149 @@
 // app.txt
-use crossterm::event::{self, Event, KeyCode};
-use ratatui::prelude::*;
-use ringbuf::Consumer;
-use rodio::{Decoder, OutputStream, Sink, Source};
+use crossterm::{
+    cursor::MoveTo,
+    event::{self, Event, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
+    queue,
+    style::Print,
+};
+use rand::seq::SliceRandom;
+use ratatui::{prelude::*, widgets::ListState};
+use ringbuf::{Consumer, HeapProducer};
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
     error::Error,
     fs,
-    io::{self, BufReader},
+    io::{self, Write},
     path::{Path, PathBuf},
-    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{Receiver, TryRecvError},
+        Arc, Mutex,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
+};
+use crate::{
+    album_art::{can_display, clear_escape, detect_image_protocol, image_escape, ImageProtocol},
+    browser::{songs_in, BrowserFocus, FolderBrowser},
+    command::{parse_command, Command},
+    config::{config_path, expand_home, Config},
+    decoder::{open_song_source, SongSource},
+    desktop::{copy_to_clipboard, open_in_file_manager, paste_from_clipboard},
+    equalizer::{Band, EqGains, Equalizer, GAIN_STEP_DB},
+    favorites::{load_favorites, save_favorites},
+    keymap::{Action, Keymap},
+    library::{spawn_loader, LibraryEvent, LibrarySource},
+    line_edit::{apply_key, insert, Edit},
+    lyrics::{load_lyrics, Lyrics},
+    m3u::{save_m3u, unused_playlist_path, M3uEntry},
+    metadata::{read_cover_art, read_metadata, AudioInfo, CoverArt, TrackMetadata},
+    output::{device_connected, list_output_devices, open_output},
+    play_counts::{load_play_counts, save_play_counts},
+    recent_dirs::{load_recent_dirs, push_recent_dir, save_recent_dirs},
+    search::fuzzy_score,
+    session::{canonical_dirs, load_session, save_session, saved_playlist, Session},
+    settings::{load_settings, save_settings, PlayerSettings},
+    recorder::{RecordTap, Recorder},
+    silence::SilenceSkipper,
+    theme::Theme,
+    ui::ui,
 };
-use crate::ui::ui;
+#[cfg(feature = "mpris")]
+use crate::mpris::{MediaCommand, MprisHandle, NowPlaying};
+#[cfg(unix)]
+use crate::notify::Notifier;
+#[cfg(feature = "scrobble")]
+use crate::scrobbler::{Scrobbler, Track as ScrobbleTrack};
+
+const VOLUME_STEP: f32 = 0.05;
+/// How far the seek keys jump with Shift held.
+const LONG_SEEK_STEP: Duration = Duration::from_secs(30);
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Sentinel for `App::seek_request` meaning no seek is pending.
+const NO_SEEK: u64 = u64::MAX;
+/// Longest a decode thread sleeps on a full buffer without being woken,
+/// in case a wakeup is ever missed.
+const DECODER_PARK_TIMEOUT: Duration = Duration::from_millis(100);
+const MAX_VOLUME: f32 = 2.0;
+/// Durations the sleep timer key cycles through, in minutes, before
+/// turning the timer off again.
+const SLEEP_TIMER_MINUTES: [u64; 4] = [15, 30, 45, 60];
+/// How long before the sleep timer stops playback the volume ramps down.
+const SLEEP_FADE: Duration = Duration::from_secs(10);
+/// How far into a song playback has to get for it to count as played.
+const PLAY_COUNT_THRESHOLD: f32 = 0.9;
+/// How often to look for the output device disappearing or coming back.
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// Spectrum level for bins with no energy, below anything displayed.
+const SILENCE_DB: f32 = -120.0;
+/// Spectra kept for the waterfall: a row per half cell on the tallest
+/// likely terminal.
+const WATERFALL_ROWS: usize = 256;
+const SPEED_STEP: f32 = 0.1;
+const MIN_SPEED: f32 = 0.5;
+const MAX_SPEED: f32 = 2.0;
+
+type SampleConsumer = Consumer<f32, Arc<ringbuf::HeapRb<f32>>>;
 
 struct RingBufferSource {
-    consumer: Consumer<f32, Arc<ringbuf::HeapRb<f32>>>,
+    consumer: SampleConsumer,
+    /// Copies of the samples as they're played, so the visualizer follows
+    /// what's audible rather than what's been decoded.
+    spectrogram_tap: HeapProducer<f32>,
     channels: u16,
     sample_rate: u32,
-}
-
-impl RingBufferSource {
-    fn new(consumer: Consumer<f32, Arc<ringbuf::HeapRb<f32>>>, channels: u16, sample_rate: u32) -> Self {
-        Self { consumer, channels, sample_rate }
-    }
+    decode_finished: Arc<AtomicBool>,
+    flush: Arc<AtomicBool>,
+    samples_played: Arc<AtomicU64>,
+    /// Woken once the buffer has drained to half, if it parked on it full.
+    decoder: thread::Thread,
+    decoder_waiting: Arc<AtomicBool>,
+    /// Applied here rather than while decoding, so changes are heard
+    /// straight away instead of after the buffered audio.
+    equalizer: Equalizer,
+    /// Fed from here too, so audio dropped by a seek is never recorded.
+    record_tap: RecordTap,
 }
 
 impl Iterator for RingBufferSource {
     type Item = f32;
 
     fn next(&mut self) -> Option<f32> {
-        self.consumer.pop()
+        if self.flush.load(Ordering::SeqCst) {
+            // The decoder seeked; drop everything queued from the old position
+            self.consumer.clear();
+            self.flush.store(false, Ordering::SeqCst);
+            self.equalizer.reset();
+            self.decoder.unpark();
+        }
+        // Waking the decoder only at half full lets it refill in one batch
+        // instead of a sample at a time
+        if self.decoder_waiting.load(Ordering::Relaxed)
+            && self.consumer.len() <= self.consumer.capacity() / 2
+            && self.decoder_waiting.swap(false, Ordering::SeqCst)
+        {
+            self.decoder.unpark();
+        }
+
+        match self.consumer.pop() {
+            Some(sample) => {
+                let sample = self.equalizer.process(sample);
+                self.samples_played.fetch_add(1, Ordering::Relaxed);
+                let _ = self.spectrogram_tap.push(sample);
+                self.record_tap.push(sample);
+                Some(sample)
+            }
+            // Only end the source once the decoder is done; otherwise play
+            // silence so a momentary underrun doesn't drop it from the sink.
+            None if self.decode_finished.load(Ordering::SeqCst) => None,
+            None => Some(0.0),
+        }
     }
 }
 
@@ -55,293 +168,3101 @@ impl Source for RingBufferSource {
 pub enum InputMode {
     Normal,
     Editing,
+    Seeking,
+    Command,
+    AddingPath,
+    /// Waiting for `y` after quit was pressed mid-song.
+    ConfirmingQuit,
+}
+
+/// Parses a seek target: `MM:SS`, `H:MM:SS`, plain seconds, or a
+/// percentage of `total`.
+fn parse_seek_target(input: &str, total: Option<Duration>) -> Option<Duration> {
+    let input = input.trim();
+    if let Some(percent) = input.strip_suffix('%') {
+        let percent: f64 = percent.parse().ok()?;
+        return Some(total?.mul_f64(percent.clamp(0.0, 100.0) / 100.0));
+    }
+
+    let mut secs = 0u64;
+    for (i, part) in input.split(':').enumerate() {
+        if i > 2 || part.is_empty() {
+            return None;
+        }
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+/// State owned by the decode thread, which feeds decoded samples into the
+/// playback ring buffer and services seek requests.
+struct DecodeThread {
+    path: PathBuf,
+    source: SongSource,
+    channels: u16,
+    sample_rate: u32,
+    playback_prod: HeapProducer<f32>,
+    stop: Arc<AtomicBool>,
+    song_finished: Arc<AtomicBool>,
+    seek_request: Arc<AtomicU64>,
+    flush_playback: Arc<AtomicBool>,
+    samples_played: Arc<AtomicU64>,
+    /// Set while parked on a full buffer, for playback to wake it.
+    waiting: Arc<AtomicBool>,
+    silence_skipper: Option<SilenceSkipper>,
+}
+
+impl DecodeThread {
+    fn run(mut self) {
+        while !self.stop.load(Ordering::SeqCst) {
+            // The request stays set until the seek is done, so the UI can
+            // tell one is still under way
+            let seek_ms = self.seek_request.load(Ordering::SeqCst);
+            if seek_ms != NO_SEEK {
+                self.seek(Duration::from_millis(seek_ms));
+                // A newer request that came in meanwhile is kept for the
+                // next pass
+                let _ = self.seek_request.compare_exchange(
+                    seek_ms,
+                    NO_SEEK,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                );
+                continue;
+            }
+
+            if self.playback_prod.is_full() {
+                // Stopping and seeking wake the thread too
+                self.waiting.store(true, Ordering::SeqCst);
+                thread::park_timeout(DECODER_PARK_TIMEOUT);
+                continue;
+            }
+
+            let sample = match &mut self.silence_skipper {
+                Some(skipper) => {
+                    let sample = skipper.next(&mut self.source);
+                    // The skipped lead-in still counts toward the position
+                    let skipped = std::mem::take(&mut skipper.skipped);
+                    if skipped > 0 {
+                        self.samples_played.fetch_add(skipped, Ordering::SeqCst);
+                    }
+                    sample
+                }
+                None => self.source.next(),
+            };
+            if let Some(sample) = sample {
+                let _ = self.playback_prod.push(sample);
+            } else {
+                // Reached the end of the track (not a manual stop). Playback
+                // ends the source once the buffer has drained.
+                self.song_finished.store(true, Ordering::SeqCst);
+                break;
+            }
+        }
+    }
+
+    fn seek(&mut self, target: Duration) {
+        let channels = self.channels as u64;
+        let target_samples =
+            (target.as_secs_f64() * self.sample_rate as f64) as u64 * channels;
+
+        if self.source.try_seek(target).is_err() {
+            // Decoders without native seeking (flac, vorbis): reopen the file
+            // and decode forward to the target
+            let Ok(source) = open_song_source(&self.path) else {
+                return;
+            };
+            self.source = source;
+            for _ in 0..target_samples {
+                if self.source.next().is_none() {
+                    break;
+                }
+            }
+        }
+
+        if let Some(skipper) = &mut self.silence_skipper {
+            skipper.reset(target);
+        }
+
+        // Have the playback side drop stale samples, and don't queue new
+        // ones until it has done so
+        self.samples_played.store(target_samples, Ordering::SeqCst);
+        self.flush_playback.store(true, Ordering::SeqCst);
+        while self.flush_playback.load(Ordering::SeqCst) && !self.stop.load(Ordering::SeqCst) {
+            thread::park_timeout(DECODER_PARK_TIMEOUT);
+        }
+    }
+}
+
+/// Tells a decode thread to exit, waking it if it's parked, and waits for
+/// it.
+fn stop_decoder(stop: &AtomicBool, handle: Option<thread::JoinHandle<()>>) {
+    stop.store(true, Ordering::SeqCst);
+    if let Some(handle) = handle {
+        handle.thread().unpark();
+        handle.join().unwrap();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatMode {
+    Off,
+    One,
+    All,
+}
+
+impl RepeatMode {
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::One,
+            RepeatMode::One => RepeatMode::All,
+            RepeatMode::All => RepeatMode::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "Off",
+            RepeatMode::One => "One",
+            RepeatMode::All => "All",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    Name,
+    Modified,
+    Duration,
+    PlayCount,
+    /// The order of the playlist file or saved session the songs came
+    /// from. Once sorted another way it can't be returned to.
+    Playlist,
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Name => SortMode::Modified,
+            SortMode::Modified => SortMode::Duration,
+            SortMode::Duration => SortMode::PlayCount,
+            SortMode::PlayCount | SortMode::Playlist => SortMode::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Name => "Name",
+            SortMode::Modified => "Date modified",
+            SortMode::Duration => "Duration",
+            SortMode::PlayCount => "Most played",
+            SortMode::Playlist => "Playlist order",
+        }
+    }
+}
+
+/// What a search matches against.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SearchScope {
+    FileName,
+    Path,
+    /// Artist, title and album, or the file name for untagged songs.
+    Tags,
+}
+
+impl SearchScope {
+    pub fn next(self) -> Self {
+        match self {
+            SearchScope::FileName => SearchScope::Path,
+            SearchScope::Path => SearchScope::Tags,
+            SearchScope::Tags => SearchScope::FileName,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchScope::FileName => "File name",
+            SearchScope::Path => "Path",
+            SearchScope::Tags => "Tags",
+        }
+    }
+}
+
+/// The previous song while it fades out under the next one. Its decode
+/// thread keeps feeding its own sink until the fade completes.
+struct FadingTrack {
+    sink: Sink,
+    stop: Arc<AtomicBool>,
+    decode_thread: Option<thread::JoinHandle<()>>,
+    started: Instant,
+    duration: Duration,
+    /// The outgoing song's ReplayGain factor, kept through the fade.
+    gain: f32,
+}
+
+/// A song's running decode thread and the state shared with it.
+struct SongPipeline {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    song_finished: Arc<AtomicBool>,
+    seek_request: Arc<AtomicU64>,
+    flush_playback: Arc<AtomicBool>,
+    samples_played: Arc<AtomicU64>,
+    /// Whether the song's samples go to the recorder, cleared once it's
+    /// replaced.
+    recording: Arc<AtomicBool>,
+    decode_thread: thread::JoinHandle<()>,
+    /// Handed to the spectrogram thread once the song becomes current.
+    spectrogram_cons: SampleConsumer,
+    channels: u16,
+    sample_rate: u32,
+    total_duration: Option<Duration>,
+}
+
+/// The next song, already decoding and appended to the sink behind the
+/// current one so it starts without a gap.
+struct PreloadedSong {
+    pipeline: SongPipeline,
+    /// Taken from the front of the play queue rather than the playlist.
+    from_queue: bool,
+}
+
+/// The list next, previous and shuffle step through. Songs started from a
+/// search, the favorites or a folder keep playing from a copy of that list
+/// after the view changes.
+#[derive(PartialEq, Eq)]
+enum PlaySource {
+    Playlist,
+    List(Vec<PathBuf>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum VisualizerMode {
+    Spectrum,
+    Waveform,
+    Waterfall,
+    /// The current song's synced lyrics in place of a visualizer.
+    Lyrics,
+}
+
+impl VisualizerMode {
+    pub fn next(self) -> Self {
+        match self {
+            VisualizerMode::Spectrum => VisualizerMode::Waveform,
+            VisualizerMode::Waveform => VisualizerMode::Waterfall,
+            VisualizerMode::Waterfall => VisualizerMode::Lyrics,
+            VisualizerMode::Lyrics => VisualizerMode::Spectrum,
+        }
+    }
+}
+
+/// How loud one channel was over the samples played since the last
+/// analysis tick, as linear amplitudes.
+#[derive(Clone, Copy, Default)]
+pub struct ChannelLevel {
+    pub rms: f32,
+    pub peak: f32,
+}
+
+impl ChannelLevel {
+    fn measure(samples: &[f32]) -> ChannelLevel {
+        if samples.is_empty() {
+            return ChannelLevel::default();
+        }
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+        let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        ChannelLevel { rms, peak }
+    }
+}
+
+/// The window applied to each frame before the FFT, set with `fft_window`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FftWindow {
+    Hanning,
+    Hamming,
+    Blackman,
+    Rectangular,
+}
+
+impl FftWindow {
+    fn coefficients(self, size: usize) -> Vec<f32> {
+        let window: Vec<f64> = match self {
+            FftWindow::Hanning => apodize::hanning_iter(size).collect(),
+            FftWindow::Hamming => apodize::hamming_iter(size).collect(),
+            FftWindow::Blackman => apodize::blackman_iter(size).collect(),
+            FftWindow::Rectangular => vec![1.0; size],
+        };
+        window.into_iter().map(|w| w as f32).collect()
+    }
+}
+
+impl FromStr for FftWindow {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hanning" | "hann" => Ok(FftWindow::Hanning),
+            "hamming" => Ok(FftWindow::Hamming),
+            "blackman" => Ok(FftWindow::Blackman),
+            "rectangular" | "none" => Ok(FftWindow::Rectangular),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Popup state for choosing an audio output device.
+pub struct DevicePicker {
+    pub devices: Vec<String>,
+    pub selected: usize,
 }
 
 pub struct App {
     pub input: String,
+    /// Byte offset of the cursor in `input`.
+    pub input_cursor: usize,
+    /// The search cleared most recently, for `restore_search`.
+    last_search: Option<String>,
+    pub search_scope: SearchScope,
     pub input_mode: InputMode,
+    pub seek_input: String,
+    /// The line typed after `:` in command mode.
+    pub command_input: String,
+    /// Byte offset of the cursor in `command_input`.
+    pub command_cursor: usize,
+    /// A file or folder to add to the library.
+    pub path_input: String,
+    /// Byte offset of the cursor in `path_input`.
+    pub path_cursor: usize,
+    pub show_help: bool,
+    /// Whether the progress bar shows the time left instead of the total.
+    pub show_remaining: bool,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    pub device_picker: Option<DevicePicker>,
+    pub output_device: Option<String>,
+    /// The device the stream is actually open on.
+    pub opened_device: Option<String>,
+    /// Set when `opened_device` was unplugged; playback stays paused until
+    /// it returns or another device is chosen.
+    pub device_lost: bool,
+    last_device_check: Instant,
+    pub status_message: Option<(String, Instant)>,
+    /// Set when something visible changed and the UI should be redrawn.
+    pub needs_redraw: bool,
+    /// Where the playlist was last drawn, for mapping mouse clicks to songs.
+    pub playlist_area: Rect,
+    /// Where the progress gauge was last drawn, for click-to-seek.
+    pub progress_area: Rect,
+    /// Kept between frames so the scroll offset follows the selection
+    /// instead of resetting every redraw.
+    pub playlist_state: ListState,
+    last_click: Option<(usize, Instant)>,
     pub playlist: Vec<PathBuf>,
+    /// Feed from the background library loader; `None` once it has finished.
+    pub library_rx: Option<Receiver<LibraryEvent>>,
+    /// Files the loader has looked at so far, for the progress line.
+    pub files_scanned: usize,
+    /// Canonical paths already in the playlist when a runtime add started,
+    /// so songs added again are skipped. Empty during the initial load.
+    known_songs: HashSet<PathBuf>,
+    songs_before_load: usize,
+    pub music_dirs: Vec<PathBuf>,
+    /// Folders loaded before, most recent first, for reopening.
+    pub recent_dirs: Vec<PathBuf>,
+    /// The highlighted row while the recent folders popup is open.
+    pub recent_dirs_picker: Option<usize>,
+    /// The filtered view of the playlist while searching or showing only
+    /// favorites.
     pub search_results: Vec<PathBuf>,
+    pub favorites: HashSet<PathBuf>,
+    pub favorites_only: bool,
+    /// Set while the two-pane folder view replaces the flat playlist.
+    pub folder_browser: Option<FolderBrowser>,
+    /// Songs directly in the folder selected in the folder view.
+    pub folder_songs: Vec<PathBuf>,
+    /// How many times each song has been played through.
+    pub play_counts: HashMap<PathBuf, u32>,
+    /// Set once the current song has been heard below the counting
+    /// threshold, so resuming near the end doesn't count as a play.
+    play_count_armed: bool,
     pub _stream: Option<OutputStream>,
+    pub stream_handle: Option<OutputStreamHandle>,
     pub sink: Option<Sink>,
+    pub audio_error: Option<String>,
     pub current_song_path: Option<PathBuf>,
     pub selected_song_index: Option<usize>,
     pub is_playing: bool,
     pub spectrogram_data: Arc<Mutex<Vec<f32>>>,
+    /// The raw samples behind the latest spectrum, for the oscilloscope.
+    pub waveform_data: Arc<Mutex<Vec<f32>>>,
+    /// The most recent spectra, oldest first, for the waterfall.
+    pub spectrogram_history: Arc<Mutex<VecDeque<Vec<f32>>>>,
+    /// Per-channel loudness of the samples played since the last analysis
+    /// tick, empty when nothing is flowing.
+    pub channel_levels: Arc<Mutex<Vec<ChannelLevel>>>,
+    pub visualizer_mode: VisualizerMode,
+    /// The spectrum eased toward the latest FFT frame, so bars move
+    /// smoothly between analysis updates.
+    smoothed_bars: Vec<f32>,
+    /// Fraction of the gap to the latest spectrum closed each frame.
+    bar_smoothing: f32,
+    fft_size: usize,
+    fft_window: FftWindow,
+    /// Seconds of decoded audio the playback buffer holds.
+    buffer_seconds: f32,
+    skip_silence: bool,
+    pub peak_levels: Vec<f32>,
+    pub peak_decay: f32,
     pub audio_thread_handle: Option<thread::JoinHandle<()>>,
+    /// Hands back its sample feed when it exits.
+    pub spectrogram_thread_handle: Option<thread::JoinHandle<SampleConsumer>>,
+    /// Hides the visualizer and stops analysis, for slow or remote
+    /// terminals.
+    pub minimal: bool,
+    /// The current song's visualizer feed while minimal mode has the
+    /// analysis thread stopped.
+    idle_spectrogram_cons: Option<SampleConsumer>,
+    /// The elapsed second last drawn, so minimal mode redraws only when
+    /// the progress bar moves.
+    drawn_second: u64,
     pub stop_audio_thread: Arc<AtomicBool>,
+    pub stop_spectrogram_thread: Arc<AtomicBool>,
+    pub song_finished: Arc<AtomicBool>,
+    pub seek_request: Arc<AtomicU64>,
+    pub flush_playback: Arc<AtomicBool>,
+    pub samples_played: Arc<AtomicU64>,
+    pub current_channels: u16,
+    pub current_sample_rate: u32,
+    pub volume: f32,
+    /// Use ReplayGain tags to even out loudness between tracks.
+    pub normalize: bool,
+    confirm_quit: bool,
+    seek_step: Duration,
+    /// Where the seek keys pressed since the last tick add up to. Held keys
+    /// repeat faster than seeks finish, so they're sent once per tick.
+    pending_seek: Option<Duration>,
+    /// Equalizer band gains, read by playback as they change.
+    pub eq_gains: Arc<EqGains>,
+    /// The band the EQ keys adjust.
+    pub eq_band: Band,
+    /// Linear gain for the current track, 1.0 unless normalizing.
+    pub track_gain: f32,
+    /// When the sleep timer stops playback.
+    pub sleep_deadline: Option<Instant>,
+    /// Copies what's played to a WAV file while recording is on.
+    pub recorder: Recorder,
+    /// The current song's switch for recording its samples.
+    recording_current: Arc<AtomicBool>,
+    pub muted: bool,
+    pub volume_before_mute: f32,
+    pub playback_speed: f32,
+    pub current_song_duration: Option<Duration>,
+    pub current_metadata: Option<TrackMetadata>,
+    pub current_audio_info: Option<AudioInfo>,
+    pub cover_art: Option<CoverArt>,
+    pub lyrics: Option<Lyrics>,
+    /// Inside of the cover panel as last drawn, where the image goes.
+    pub album_art_area: Rect,
+    pub image_protocol: Option<ImageProtocol>,
+    /// The song and area the image currently on screen was drawn for.
+    drawn_art: Option<(PathBuf, Rect)>,
+    pub metadata_cache: HashMap<PathBuf, TrackMetadata>,
+    /// Songs that failed to open, skipped when advancing automatically.
+    pub unplayable: HashSet<PathBuf>,
+    pub repeat_mode: RepeatMode,
+    pub shuffle: bool,
+    pub shuffle_order: Vec<usize>,
+    play_source: PlaySource,
+    /// The last session's shuffle order, applied once the playlist it
+    /// indexes has loaded.
+    saved_shuffle_order: Vec<usize>,
+    pub sort_mode: SortMode,
+    /// Set when the playlist came from the config's playlist file, whose
+    /// own order is kept rather than saved with the session.
+    from_playlist_file: bool,
+    pub crossfade: Option<Duration>,
+    /// Fade-in for songs that start without a crossfade.
+    start_fade: Option<Duration>,
+    /// A-B repeat points in the current song; playback jumps back to A
+    /// whenever it passes B.
+    pub loop_start: Option<Duration>,
+    pub loop_end: Option<Duration>,
+    fading_out: Option<FadingTrack>,
+    preloaded: Option<PreloadedSong>,
+    /// Songs to play next, ahead of the playlist order.
+    pub queue: VecDeque<PathBuf>,
+    /// The playlist song that was playing when queued songs took over, so
+    /// the normal order carries on after it.
+    resume_after_queue: Option<PathBuf>,
+    #[cfg(feature = "mpris")]
+    mpris: Option<MprisHandle>,
+    /// How many seeks there have been and the last one's target, for MPRIS.
+    #[cfg(feature = "mpris")]
+    seeks: (u64, Duration),
+    #[cfg(unix)]
+    notifier: Option<Notifier>,
+    #[cfg(feature = "scrobble")]
+    scrobbler: Option<Scrobbler>,
+    /// The playing track as it will be scrobbled, and whether it has been.
+    #[cfg(feature = "scrobble")]
+    scrobble_track: Option<(ScrobbleTrack, bool)>,
 }
 
 impl App {
-    pub fn new() -> Result<App, Box<dyn Error>> {
-        let (_stream, stream_handle) = match OutputStream::try_default() {
-            Ok((stream, handle)) => (Some(stream), Some(handle)),
-            Err(_) => (None, None),
+    pub fn new(config: Config) -> Result<App, Box<dyn Error>> {
+        let (_stream, stream_handle, sink, opened_device, audio_error, device_warning) =
+            match open_output(config.output_device.as_deref()) {
+                Ok(output) => (
+                    Some(output.stream),
+                    Some(output.handle),
+                    Some(output.sink),
+                    output.device_name,
+                    None,
+                    output.warning,
+                ),
+                Err(e) => (None, None, None, None, Some(e), None),
+            };
+        let mut startup_message = device_warning;
+        let session = if config.resume_on_start { load_session() } else { None };
+        // The last session's playlist stands in for scanning the same folders
+        let restored_playlist = match (&config.playlist_file, &session) {
+            (None, Some(session)) => saved_playlist(session, &config.music_dirs),
+            _ => None,
+        };
+        let keep_order = config.playlist_file.is_some() || restored_playlist.is_some();
+        let saved_shuffle_order = match &session {
+            Some(session) if restored_playlist.is_some() => session.shuffle_order.clone(),
+            _ => Vec::new(),
+        };
+        let library_source = match config.playlist_file.clone().or(restored_playlist.clone()) {
+            Some(playlist_file) => LibrarySource::PlaylistFile(playlist_file),
+            None => {
+                // First run: make the folder rather than refusing to start
+                let any_exists = config.music_dirs.iter().any(|dir| dir.is_dir());
+                if let Some(dir) = config.music_dirs.first().filter(|_| !any_exists) {
+                    startup_message = Some(match fs::create_dir_all(dir) {
+                        Ok(()) => format!(
+                            "Created music directory {} - add songs there",
+                            dir.display()
+                        ),
+                        Err(e) => format!(
+                            "No music directory at {} ({}). Set `music_dirs` in {}",
+                            dir.display(),
+                            e,
+                            config_path().map_or_else(
+                                || "the config file".to_string(),
+                                |p| p.display().to_string()
+                            )
+                        ),
+                    });
+                }
+                LibrarySource::Directories(config.music_dirs.clone())
+            }
         };
-        let sink = stream_handle.as_ref().map(|h| Sink::try_new(h).unwrap());
-        let playlist = Self::load_playlist("music")?;
-        let selected_song_index = if playlist.is_empty() { None } else { Some(0) };
-        let spectrogram_data = Arc::new(Mutex::new(vec![0.0; 512]));
+        let spectrogram_data = Arc::new(Mutex::new(vec![SILENCE_DB; config.fft_size / 2]));
 
-        Ok(App {
+        let mut app = App {
             input: String::new(),
+            input_cursor: 0,
+            last_search: None,
+            search_scope: SearchScope::FileName,
             input_mode: InputMode::Normal,
-            playlist,
+            seek_input: String::new(),
+            command_input: String::new(),
+            command_cursor: 0,
+            path_input: String::new(),
+            path_cursor: 0,
+            show_help: false,
+            show_remaining: false,
+            theme: config.theme,
+            keymap: config.keymap.clone(),
+            device_picker: None,
+            output_device: config.output_device.clone(),
+            opened_device,
+            device_lost: false,
+            last_device_check: Instant::now(),
+            status_message: startup_message.map(|m| (m, Instant::now())),
+            needs_redraw: true,
+            playlist_area: Rect::default(),
+            progress_area: Rect::default(),
+            playlist_state: ListState::default(),
+            last_click: None,
+            playlist: Vec::new(),
+            library_rx: Some(spawn_loader(library_source)),
+            files_scanned: 0,
+            known_songs: HashSet::new(),
+            songs_before_load: 0,
+            music_dirs: config.music_dirs.clone(),
+            recent_dirs: load_recent_dirs(),
+            recent_dirs_picker: None,
             search_results: vec![],
+            favorites: load_favorites(),
+            favorites_only: false,
+            folder_browser: None,
+            folder_songs: Vec::new(),
+            play_counts: load_play_counts(),
+            play_count_armed: false,
             _stream,
+            stream_handle,
             sink,
+            audio_error,
             current_song_path: None,
-            selected_song_index,
+            selected_song_index: None,
             is_playing: false,
             spectrogram_data,
+            waveform_data: Arc::new(Mutex::new(Vec::new())),
+            spectrogram_history: Arc::new(Mutex::new(VecDeque::new())),
+            channel_levels: Arc::new(Mutex::new(Vec::new())),
+            visualizer_mode: VisualizerMode::Spectrum,
+            smoothed_bars: Vec::new(),
+            bar_smoothing: config.bar_smoothing.clamp(0.01, 1.0),
+            fft_size: config.fft_size,
+            buffer_seconds: config.buffer_seconds,
+            skip_silence: config.skip_silence,
+            fft_window: config.fft_window,
+            peak_levels: Vec::new(),
+            peak_decay: config.peak_decay,
             audio_thread_handle: None,
+            spectrogram_thread_handle: None,
+            minimal: config.minimal,
+            idle_spectrogram_cons: None,
+            drawn_second: 0,
             stop_audio_thread: Arc::new(AtomicBool::new(false)),
-        })
-    }
+            stop_spectrogram_thread: Arc::new(AtomicBool::new(false)),
+            song_finished: Arc::new(AtomicBool::new(false)),
+            seek_request: Arc::new(AtomicU64::new(NO_SEEK)),
+            flush_playback: Arc::new(AtomicBool::new(false)),
+            samples_played: Arc::new(AtomicU64::new(0)),
+            current_channels: 0,
+            current_sample_rate: 0,
+            volume: 1.0,
+            normalize: config.normalize,
+            confirm_quit: config.confirm_quit,
+            seek_step: Duration::from_secs(config.seek_step_secs.max(1)),
+            pending_seek: None,
+            eq_gains: Arc::new(EqGains::default()),
+            eq_band: Band::Bass,
+            track_gain: 1.0,
+            sleep_deadline: None,
+            recorder: Recorder::spawn(),
+            recording_current: Arc::new(AtomicBool::new(false)),
+            muted: false,
+            volume_before_mute: 1.0,
+            playback_speed: 1.0,
+            current_song_duration: None,
+            current_metadata: None,
+            current_audio_info: None,
+            cover_art: None,
+            lyrics: None,
+            album_art_area: Rect::default(),
+            image_protocol: detect_image_protocol(),
+            drawn_art: None,
+            metadata_cache: HashMap::new(),
+            unplayable: HashSet::new(),
+            repeat_mode: RepeatMode::Off,
+            shuffle: false,
+            shuffle_order: Vec::new(),
+            play_source: PlaySource::Playlist,
+            saved_shuffle_order,
+            sort_mode: if keep_order { SortMode::Playlist } else { SortMode::Name },
+            from_playlist_file: config.playlist_file.is_some(),
+            crossfade: config
+                .crossfade_secs
+                .filter(|secs| secs.is_finite() && *secs > 0.0)
+                .map(Duration::from_secs_f32),
+            start_fade: config
+                .start_fade_secs
+                .filter(|secs| secs.is_finite() && *secs > 0.0)
+                .map(Duration::from_secs_f32),
+            fading_out: None,
+            preloaded: None,
+            queue: VecDeque::new(),
+            resume_after_queue: None,
+            #[cfg(feature = "mpris")]
+            mpris: crate::mpris::spawn()
+                .map_err(|e| eprintln!("MPRIS unavailable: {}", e))
+                .ok(),
+            #[cfg(feature = "mpris")]
+            seeks: (0, Duration::ZERO),
+            #[cfg(unix)]
+            notifier: config.notifications.then(Notifier::spawn),
+            #[cfg(feature = "scrobble")]
+            scrobbler: config.lastfm.clone().map(Scrobbler::spawn),
+            #[cfg(feature = "scrobble")]
+            scrobble_track: None,
+            loop_start: None,
+            loop_end: None,
+        };
 
-    fn load_playlist<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>, io::Error> {
-        let mut playlist = Vec::new();
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                playlist.extend(Self::load_playlist(&path)?);
-            } else if path.is_file() {
-                if let Some(extension) = path.extension() {
-                    if let Some(extension_str) = extension.to_str() {
-                        match extension_str {
-                            "mp3" | "flac" | "wav" => {
-                                playlist.push(path.canonicalize()?);
-                            }
-                            _ => {}
-                        }
-                    }
-                }
+        let mut settings = load_settings();
+        if let Some(percent) = config.default_volume {
+            settings.volume = percent / 100.0;
+            settings.muted = false;
+        }
+        app.apply_settings(settings);
+        if config.playlist_file.is_none() {
+            // Listed first to last, so the first ends up most recent
+            for dir in config.music_dirs.iter().rev() {
+                app.remember_dir(dir);
             }
+            app.save_recent_dirs();
         }
-        Ok(playlist)
-    }
-
-    pub fn play_pause(&mut self) {
-        if let Some(sink) = &self.sink {
-            if sink.is_paused() {
-                sink.play();
-                self.is_playing = true;
-            } else {
-                sink.pause();
-                self.is_playing = false;
+        if let Some(song) = &config.play_on_start {
+            if let Err(e) = app.play_song_by_path(song) {
+                app.set_status_message(format!("Error playing {}: {}", song.display(), e));
             }
+        } else if let Some(session) = session {
+            app.restore_session(session);
         }
+
+        Ok(app)
     }
 
-    pub fn is_seekable(&self) -> bool {
-        self.sink.as_ref().map_or(false, |s| !s.empty())
+    pub fn is_library_loading(&self) -> bool {
+        self.library_rx.is_some()
     }
 
-    pub fn seek_forward(&mut self) {
-        if self.is_seekable() {
-            if let Some(sink) = &self.sink {
-                let current_pos = sink.get_pos();
-                let new_pos = current_pos + Duration::from_secs(5);
-                if let Err(e) = sink.try_seek(new_pos) {
-                    if !e.to_string().contains("end of stream") {
-                        eprintln!("Error seeking forward: {}", e);
+    /// Applies whatever the background library loader has sent since the
+    /// last tick.
+    pub fn poll_library(&mut self) {
+        let Some(rx) = &self.library_rx else {
+            return;
+        };
+        let mut events = Vec::new();
+        let finished = loop {
+            match rx.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+
+        let received = !events.is_empty();
+        for event in events {
+            match event {
+                LibraryEvent::Scanned(count) => self.files_scanned = count,
+                LibraryEvent::Songs(mut songs) => {
+                    if !self.known_songs.is_empty() {
+                        songs.retain(|song| self.known_songs.insert(song.clone()));
+                    }
+                    self.playlist.extend(songs);
+                    if self.selected_song_index.is_none() && !self.active_songs().is_empty() {
+                        self.selected_song_index = Some(0);
+                    }
+                }
+                LibraryEvent::Metadata(path, metadata) => {
+                    if self.current_song_path.as_ref() == Some(&path) {
+                        self.current_metadata = Some(metadata.clone());
                     }
+                    self.metadata_cache.insert(path, metadata);
                 }
+                LibraryEvent::Warning(message) => self.set_status_message(message),
+                LibraryEvent::Finished => {}
             }
         }
-    }
 
-    pub fn seek_backward(&mut self) {
-        if self.is_seekable() {
-            if let Some(sink) = &self.sink {
-                let current_pos = sink.get_pos();
-                let new_pos = if current_pos > Duration::from_secs(5) {
-                    current_pos - Duration::from_secs(5)
-                } else {
-                    Duration::ZERO
-                };
-                if let Err(e) = sink.try_seek(new_pos) {
-                    if !e.to_string().contains("end of stream") {
-                        eprintln!("Error seeking backward: {}", e);
-                    }
-                }
+        if received || finished {
+            self.needs_redraw = true;
+        }
+        if received {
+            self.refresh_folder_view();
+        }
+        if finished {
+            self.library_rx = None;
+            self.known_songs.clear();
+            self.on_library_loaded();
+            // Warnings from loading take precedence over the summary
+            if self.status_message().is_none() {
+                self.set_status_message(format!(
+                    "Loaded {} songs ({} files scanned)",
+                    self.playlist.len() - self.songs_before_load,
+                    self.files_scanned
+                ));
             }
         }
     }
 
-    pub fn play_selected_song(&mut self) -> Result<(), Box<dyn Error>> {
-        let song_to_play = if self.input.is_empty() {
-            self.selected_song_index.and_then(|i| self.playlist.get(i).cloned())
-        } else {
-            self.selected_song_index.and_then(|i| self.search_results.get(i).cloned())
-        };
-
-        if let Some(song_path) = song_to_play {
-            self.play_song_by_path(&song_path)?;
+    /// Brings search results, shuffle order and selection up to date with
+    /// the fully loaded playlist.
+    fn on_library_loaded(&mut self) {
+        self.sort_playlist();
+        if self.is_filtered() {
+            self.update_search();
+        }
+        // The saved order only fits if every saved song is still there
+        let saved_order = std::mem::take(&mut self.saved_shuffle_order);
+        let mut positions = saved_order.clone();
+        positions.sort_unstable();
+        let fits = positions.into_iter().eq(0..self.playlist.len());
+        if self.play_source == PlaySource::Playlist && fits {
+            self.shuffle_order = saved_order;
+        }
+        let current = self.current_song_path.as_ref();
+        if let Some(index) = current.and_then(|c| self.active_songs().iter().position(|p| p == c)) {
+            self.selected_song_index = Some(index);
         }
+    }
 
-        Ok(())
+    pub fn cycle_sort_mode(&mut self) {
+        self.sort_mode = self.sort_mode.next();
+        self.sort_playlist();
     }
 
-    fn play_song_by_path(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
-        if let Some(sink) = &self.sink {
-            sink.stop();
-            sink.clear();
+    /// Sorts the playlist by the current sort mode, keeping the selection on
+    /// the same song. Playback is unaffected.
+    pub fn sort_playlist(&mut self) {
+        let selected = self.selected_song_index.and_then(|i| self.active_songs().get(i).cloned());
 
-            self.stop_audio_thread.store(true, Ordering::SeqCst);
-            if let Some(handle) = self.audio_thread_handle.take() {
-                handle.join().unwrap();
+        match self.sort_mode {
+            SortMode::Name => self.playlist.sort_by_cached_key(|p| {
+                p.file_name().unwrap_or_default().to_string_lossy().to_lowercase()
+            }),
+            // Most recently modified first
+            SortMode::Modified => self.playlist.sort_by_cached_key(|p| {
+                std::cmp::Reverse(fs::metadata(p).and_then(|m| m.modified()).ok())
+            }),
+            // Shortest first, unknown lengths last
+            SortMode::Duration => {
+                let cache = &self.metadata_cache;
+                self.playlist.sort_by_cached_key(|p| {
+                    let duration = cache.get(p).and_then(|m| m.duration);
+                    (duration.is_none(), duration)
+                })
             }
-            self.stop_audio_thread.store(false, Ordering::SeqCst);
-
-            let file = BufReader::new(fs::File::open(path)?);
-            let source = Decoder::new(file)?;
-            let channels = source.channels();
-            let sample_rate = source.sample_rate();
+            // Most played first, ties by name
+            SortMode::PlayCount => {
+                let counts = &self.play_counts;
+                self.playlist.sort_by_cached_key(|p| {
+                    let name = p.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+                    (std::cmp::Reverse(counts.get(p).copied().unwrap_or(0)), name)
+                })
+            }
+            SortMode::Playlist => {}
+        }
 
-            let playback_rb = ringbuf::HeapRb::<f32>::new(sample_rate as usize * 5);
-            let (mut playback_prod, playback_cons) = playback_rb.split();
+        if self.favorites_only && self.input.is_empty() {
+            // The favorites view follows playlist order
+            self.refilter();
+        }
+        if self.input.is_empty() {
+            if let Some(selected) = selected {
+                self.selected_song_index =
+                    self.active_songs().iter().position(|p| p == &selected);
+            }
+        }
+        if self.play_source == PlaySource::Playlist {
+            self.rebuild_shuffle_order();
+        }
+        self.refresh_folder_view();
+    }
 
-            let spectrogram_rb = ringbuf::HeapRb::<f32>::new(sample_rate as usize * 5);
-            let (mut spectrogram_prod, mut spectrogram_cons) = spectrogram_rb.split();
+    /// Scans the typed file or folder in the background and appends any
+    /// songs not already in the playlist.
+    fn submit_add_path(&mut self) {
+        let input = std::mem::take(&mut self.path_input);
+        self.input_mode = InputMode::Normal;
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+        let path = expand_home(input);
+        if !path.exists() {
+            self.set_status_message(format!("No such file or directory: {}", path.display()));
+            return;
+        }
+        if self.is_library_loading() {
+            self.set_status_message("Wait for the library to finish loading");
+            return;
+        }
+        self.load_library_path(path);
+    }
 
-            let stop_audio_thread = self.stop_audio_thread.clone();
-            let audio_thread_handle = thread::spawn(move || {
-                let mut source = source.convert_samples::<f32>();
-                while !stop_audio_thread.load(Ordering::SeqCst) {
-                    if let Some(sample) = source.next() {
-                        while playback_prod.is_full() {
-                            thread::sleep(Duration::from_millis(1));
-                        }
-                        let _ = playback_prod.push(sample);
-                        let _ = spectrogram_prod.push(sample);
-                    } else {
-                        break;
-                    }
-                }
-            });
-            self.audio_thread_handle = Some(audio_thread_handle);
-
-            let spectrogram_data = self.spectrogram_data.clone();
-            thread::spawn(move || {
-                let fft_size = 1024;
-                let window = apodize::hanning_iter(fft_size).map(|f| f as f32).collect::<Vec<_>>();
-                let mut planner = rustfft::FftPlanner::new();
-                let fft = planner.plan_fft_forward(fft_size);
-                let mut buffer: Vec<f32> = Vec::with_capacity(fft_size);
-
-                loop {
-                    // Collect samples at a fixed rate regardless of UI updates
-                    while buffer.len() < fft_size && spectrogram_cons.len() > 0 {
-                        if let Some(sample) = spectrogram_cons.pop() {
-                            buffer.push(sample);
-                        }
-                    }
+    /// Starts scanning `path` into the playlist, skipping songs already in
+    /// it. Folders are added to the recent folders.
+    fn load_library_path(&mut self, path: PathBuf) {
+        if path.is_dir() {
+            self.remember_dir(&path);
+            self.save_recent_dirs();
+        }
+        self.known_songs =
+            self.playlist.iter().map(|p| p.canonicalize().unwrap_or_else(|_| p.clone())).collect();
+        self.songs_before_load = self.playlist.len();
+        self.files_scanned = 0;
+        self.library_rx = Some(spawn_loader(LibrarySource::Directories(vec![path])));
+    }
 
-                    // Process FFT when we have enough samples
-                    if buffer.len() >= fft_size {
-                        let mut complex_buffer: Vec<_> = buffer
-                            .drain(..fft_size)
-                            .zip(window.iter())
-                            .map(|(s, w)| rustfft::num_complex::Complex::new(s * w, 0.0))
-                            .collect();
-
-                        fft.process(&mut complex_buffer);
-
-                        let mut spectrogram_data = spectrogram_data.lock().unwrap();
-                        *spectrogram_data = complex_buffer[..fft_size / 2]
-                            .iter()
-                            .map(|c| (c.norm_sqr().sqrt() * 2.0 / fft_size as f32).log10() * 20.0)
-                            .map(|v| if v.is_nan() || v.is_infinite() { 0.0 } else { v })
-                            .collect();
-                    }
-                    
-                    // Consistent update rate - 30 FPS for smooth visualization
-                    thread::sleep(Duration::from_millis(16));
-                }
-            });
+    fn remember_dir(&mut self, dir: &Path) {
+        if dir.is_dir() {
+            push_recent_dir(&mut self.recent_dirs, &dir.canonicalize().unwrap_or(dir.into()));
+        }
+    }
 
-            let source = RingBufferSource::new(playback_cons, channels, sample_rate);
-            sink.append(source);
-            sink.play();
-            self.current_song_path = Some(path.to_path_buf());
-            self.is_playing = true;
+    fn save_recent_dirs(&mut self) {
+        if let Err(e) = save_recent_dirs(&self.recent_dirs) {
+            self.set_status_message(format!("Error saving recent folders: {}", e));
         }
+    }
 
-        Ok(())
+    pub fn open_recent_dirs_picker(&mut self) {
+        if self.recent_dirs.is_empty() {
+            self.set_status_message("No recent folders yet");
+            return;
+        }
+        self.recent_dirs_picker = Some(0);
     }
 
-    pub fn next_song(&mut self) -> Result<(), Box<dyn Error>> {
-        let songs_to_play = if self.input.is_empty() {
-            &self.playlist
-        } else {
-            &self.search_results
+    /// Enter opens the highlighted folder in place of the playlist, `a`
+    /// adds it to the playlist, Delete forgets it.
+    pub fn handle_recent_dirs_key(&mut self, code: KeyCode) {
+        let Some(selected) = self.recent_dirs_picker else {
+            return;
         };
+        let len = self.recent_dirs.len();
+        match code {
+            KeyCode::Up => self.recent_dirs_picker = Some((selected + len - 1) % len),
+            KeyCode::Down => self.recent_dirs_picker = Some((selected + 1) % len),
+            KeyCode::Enter | KeyCode::Char('a') => {
+                self.recent_dirs_picker = None;
+                let dir = self.recent_dirs[selected].clone();
+                self.open_recent_dir(dir, code == KeyCode::Enter);
+            }
+            KeyCode::Delete => {
+                self.recent_dirs.remove(selected);
+                self.save_recent_dirs();
+                self.recent_dirs_picker = match self.recent_dirs.len() {
+                    0 => None,
+                    len => Some(selected.min(len - 1)),
+                };
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.recent_dirs_picker = None,
+            _ => {}
+        }
+    }
 
-        if songs_to_play.is_empty() {
-            return Ok(());
+    /// Loads a folder from the recent folders, either replacing the
+    /// playlist or adding to it.
+    fn open_recent_dir(&mut self, dir: PathBuf, replace: bool) {
+        if !dir.is_dir() {
+            self.set_status_message(format!("No such directory: {}", dir.display()));
+            return;
+        }
+        if self.is_library_loading() {
+            self.set_status_message("Wait for the library to finish loading");
+            return;
+        }
+        if replace {
+            // The playing song carries on; it just isn't listed any more
+            self.playlist.clear();
+            self.selected_song_index = None;
+            self.clear_search();
+            self.refresh_folder_view();
+            self.music_dirs = vec![dir.clone()];
         }
+        self.load_library_path(dir);
+    }
 
-        let current_index = self
-            .current_song_path
+    pub fn open_device_picker(&mut self) {
+        let devices = list_output_devices();
+        if devices.is_empty() {
+            self.set_status_message("No output devices found");
+            return;
+        }
+        let selected = self
+            .output_device
             .as_ref()
-            .and_then(|p| songs_to_play.iter().position(|s| s == p));
+            .and_then(|current| devices.iter().position(|d| d == current))
+            .unwrap_or(0);
+        self.device_picker = Some(DevicePicker { devices, selected });
+    }
 
-        let next_index = match current_index {
-            Some(i) => (i + 1) % songs_to_play.len(),
-            None => 0,
+    pub fn handle_device_picker_key(&mut self, code: KeyCode) {
+        let Some(picker) = &mut self.device_picker else {
+            return;
         };
+        let len = picker.devices.len();
+        match code {
+            KeyCode::Up => picker.selected = (picker.selected + len - 1) % len,
+            KeyCode::Down => picker.selected = (picker.selected + 1) % len,
+            KeyCode::Enter => {
+                let device = picker.devices[picker.selected].clone();
+                self.device_picker = None;
+                self.switch_output_device(device);
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.device_picker = None,
+            _ => {}
+        }
+    }
 
-        let next_path = songs_to_play[next_index].clone();
-        self.play_song_by_path(&next_path)?;
-        self.selected_song_index = Some(next_index);
-
-        Ok(())
+    pub fn switch_output_device(&mut self, device: String) {
+        self.output_device = Some(device);
+        self.reopen_output();
     }
 
-    pub fn previous_song(&mut self) -> Result<(), Box<dyn Error>> {
-        let songs_to_play = if self.input.is_empty() {
-            &self.playlist
-        } else {
-            &self.search_results
-        };
+    /// Reopens audio output on the preferred device, resuming the current
+    /// song at the same position.
+    fn reopen_output(&mut self) {
+        let resume = self.current_song_path.clone().map(|path| (path, self.elapsed()));
+        let was_playing = self.is_playing;
+        self.reset_playback();
+        self.device_lost = false;
 
-        if songs_to_play.is_empty() {
-            return Ok(());
+        match open_output(self.output_device.as_deref()) {
+            Ok(output) => {
+                self._stream = Some(output.stream);
+                self.stream_handle = Some(output.handle);
+                self.sink = Some(output.sink);
+                self.audio_error = None;
+                let name = output.device_name.as_deref().unwrap_or("default").to_string();
+                self.opened_device = output.device_name;
+                self.set_status_message(
+                    output.warning.unwrap_or_else(|| format!("Output device: {}", name)),
+                );
+            }
+            Err(e) => {
+                self._stream = None;
+                self.stream_handle = None;
+                self.sink = None;
+                self.opened_device = None;
+                self.audio_error = Some(e.clone());
+                self.set_status_message(e);
+            }
         }
 
-        let current_index = self
-            .current_song_path
-            .as_ref()
-            .and_then(|p| songs_to_play.iter().position(|s| s == p));
-
-        let prev_index = match current_index {
-            Some(i) => {
-                if i == 0 {
-                    songs_to_play.len() - 1
-                } else {
-                    i - 1
-                }
+        self.is_playing = false;
+        if let Some((path, position)) = resume {
+            if self.sink.is_none() || self.play_song_by_path(&path).is_err() {
+                self.current_song_path = None;
+                return;
             }
-            None => 0,
+            self.seek_to(position);
+            if !was_playing {
+                self.play_pause();
+            }
+        }
+    }
+
+    /// Pauses when the output device disappears (e.g. Bluetooth headphones
+    /// disconnecting) and reopens it once it's back. Only polled while
+    /// playing or waiting for the device, since listing devices isn't free.
+    pub fn check_output_device(&mut self) {
+        if !(self.is_playing || self.device_lost)
+            || self.last_device_check.elapsed() < DEVICE_CHECK_INTERVAL
+        {
+            return;
+        }
+        self.last_device_check = Instant::now();
+        let Some(name) = self.opened_device.clone() else {
+            return;
         };
+        let connected = device_connected(&name);
+        if self.device_lost && connected {
+            self.reopen_output();
+            self.set_status_message(format!("Output device '{}' reconnected", name));
+        } else if !self.device_lost && !connected {
+            self.device_lost = true;
+            if let Some(sink) = &self.sink {
+                sink.pause();
+            }
+            self.is_playing = false;
+            self.needs_redraw = true;
+            self.set_status_message(format!("Output device '{}' disconnected, paused", name));
+        }
+    }
 
-        let prev_path = songs_to_play[prev_index].clone();
-        self.play_song_by_path(&prev_path)?;
-        self.selected_song_index = Some(prev_index);
+    /// Shows a short-lived message in the status bar.
+    pub fn set_status_message(&mut self, message: impl Into<String>) {
+        self.status_message = Some((message.into(), Instant::now()));
+    }
 
-        Ok(())
+    /// Drops the status message once it has been shown long enough.
+    pub fn expire_status_message(&mut self) {
+        if self.status_message.is_some() && self.status_message().is_none() {
+            self.status_message = None;
+            self.needs_redraw = true;
+        }
     }
 
-    pub fn select_next(&mut self) {
-        let songs_to_play = if self.input.is_empty() {
+    /// The status message, if it hasn't expired yet.
+    pub fn status_message(&self) -> Option<&str> {
+        self.status_message
+            .as_ref()
+            .filter(|(_, shown_at)| shown_at.elapsed() < STATUS_MESSAGE_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Writes the in-memory playlist to a new m3u file in the first music
+    /// directory.
+    pub fn export_playlist(&mut self) {
+        let dir = self.music_dirs.first().cloned().unwrap_or_else(|| PathBuf::from("."));
+        let path = unused_playlist_path(&dir);
+        let message = match save_m3u(&path, &self.m3u_entries()) {
+            Ok(()) => format!("Saved playlist to {}", path.display()),
+            Err(e) => format!("Failed to save {}: {}", path.display(), e),
+        };
+        self.set_status_message(message);
+    }
+
+    /// Starts recording into the first music folder, or stops and saves
+    /// the recording.
+    pub fn toggle_recording(&mut self) {
+        if self.recorder.is_recording() {
+            self.finish_recording();
+            return;
+        }
+        let dir = self.music_dirs.first().cloned().unwrap_or_else(|| PathBuf::from("."));
+        self.recorder.start(&dir);
+        self.set_status_message(format!("Recording to {}", dir.display()));
+    }
+
+    /// Ends the recording, if there is one, and says where it went.
+    fn finish_recording(&mut self) {
+        let message = match self.recorder.finish() {
+            None => return,
+            Some(Ok(paths)) => match paths.as_slice() {
+                [] => "Stopped recording; nothing was played".to_string(),
+                [path] => format!("Saved recording to {}", path.display()),
+                [first, rest @ ..] => {
+                    format!("Saved recording to {} and {} more", first.display(), rest.len())
+                }
+            },
+            Some(Err(e)) => format!("Recording failed: {}", e),
+        };
+        self.set_status_message(message);
+    }
+
+    /// Shows failures reported by background threads.
+    pub fn check_background_errors(&mut self) {
+        #[cfg(unix)]
+        if let Some(e) = self.notifier.as_ref().and_then(Notifier::take_error) {
+            self.set_status_message(e);
+        }
+        #[cfg(feature = "mpris")]
+        if let Some(e) = self.mpris.as_ref().and_then(MprisHandle::take_error) {
+            self.set_status_message(e);
+        }
+        #[cfg(feature = "scrobble")]
+        if let Some(e) = self.scrobbler.as_ref().and_then(Scrobbler::take_error) {
+            self.set_status_message(e);
+        }
+        if let Some(e) = self.recorder.take_error() {
+            self.set_status_message(e);
+        }
+    }
+
+    /// The playlist in its current order, titled where tags are known.
+    fn m3u_entries(&self) -> Vec<M3uEntry<'_>> {
+        self.playlist
+            .iter()
+            .map(|song| {
+                let metadata = self.metadata_cache.get(song);
+                M3uEntry {
+                    path: song,
+                    title: metadata.and_then(TrackMetadata::display_name),
+                    duration: metadata.and_then(|m| m.duration),
+                }
+            })
+            .collect()
+    }
+
+    /// Reloads the song from the previous session, paused at its saved
+    /// position.
+    fn restore_session(&mut self, session: Session) {
+        let Some(song) = session.song else {
+            return;
+        };
+        if self.sink.is_none() || self.play_song_by_path(&song).is_err() {
+            return;
+        }
+        self.play_pause();
+        self.seek_to(session.position);
+    }
+
+    /// Records the current song and position for `restore_session`, and
+    /// the playlist's order and shuffle order for the next launch.
+    pub fn save_session(&self) -> io::Result<()> {
+        let restorable = !self.from_playlist_file;
+        // Shuffle order only indexes the playlist itself when playing from it
+        let whole_playlist = self.play_source == PlaySource::Playlist;
+        let session = Session {
+            song: self.current_song_path.clone(),
+            position: self.elapsed(),
+            music_dirs: if restorable { canonical_dirs(&self.music_dirs) } else { Vec::new() },
+            shuffle_order: if self.shuffle && whole_playlist {
+                self.shuffle_order.clone()
+            } else {
+                Vec::new()
+            },
+        };
+        let entries = if restorable { self.m3u_entries() } else { Vec::new() };
+        save_session(&session, &entries)
+            .map_err(|e| io::Error::new(e.kind(), format!("Error saving session: {}", e)))
+    }
+
+    fn apply_settings(&mut self, settings: PlayerSettings) {
+        self.set_volume(settings.volume);
+        if settings.muted {
+            self.toggle_mute();
+        }
+        self.set_speed(settings.speed);
+        self.repeat_mode = settings.repeat_mode;
+        self.shuffle = settings.shuffle;
+        for (band, db) in Band::ALL.into_iter().zip(settings.eq) {
+            self.eq_gains.set(band, db);
+        }
+    }
+
+    /// Saves volume, speed, repeat, shuffle and the EQ for the next launch.
+    pub fn save_settings(&self) -> io::Result<()> {
+        let settings = PlayerSettings {
+            volume: if self.muted { self.volume_before_mute } else { self.volume },
+            muted: self.muted,
+            speed: self.playback_speed,
+            repeat_mode: self.repeat_mode,
+            shuffle: self.shuffle,
+            eq: self.eq_gains.all(),
+        };
+        save_settings(&settings)
+            .map_err(|e| io::Error::new(e.kind(), format!("Error saving settings: {}", e)))
+    }
+
+    pub fn play_pause(&mut self) {
+        if self.device_lost {
+            self.set_status_message("Output device disconnected; choose another to keep playing");
+            return;
+        }
+        if self.current_song_path.is_none() {
+            // Nothing loaded (e.g. after a stop), so start the selection instead
+            let result = self.play_selected_song();
+            self.report_error(result);
+            return;
+        }
+        self.finish_fade_out();
+        if let Some(sink) = &self.sink {
+            if sink.is_paused() {
+                sink.play();
+                self.is_playing = true;
+            } else {
+                sink.pause();
+                self.is_playing = false;
+            }
+        }
+    }
+
+    /// Steps the sleep timer through the preset durations, counting each
+    /// from now, then off.
+    pub fn cycle_sleep_timer(&mut self) {
+        let remaining = self.sleep_remaining().map_or(0, |r| r.as_secs().div_ceil(60));
+        let next = SLEEP_TIMER_MINUTES.iter().find(|&&minutes| minutes > remaining);
+        self.set_sleep_timer(next.copied());
+    }
+
+    /// Starts a sleep timer of `minutes`, or cancels it with `None`.
+    pub fn set_sleep_timer(&mut self, minutes: Option<u64>) {
+        match minutes {
+            Some(minutes) => {
+                self.sleep_deadline = Some(Instant::now() + Duration::from_secs(minutes * 60));
+                self.set_status_message(format!("Sleep timer: {} min", minutes));
+            }
+            None => {
+                self.sleep_deadline = None;
+                // Undo any fade already under way
+                self.apply_volume();
+                self.set_status_message("Sleep timer off");
+            }
+        }
+    }
+
+    pub fn sleep_remaining(&self) -> Option<Duration> {
+        self.sleep_deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Fades out over the timer's last seconds and stops playback when it
+    /// runs out.
+    pub fn check_sleep_timer(&mut self) {
+        let Some(remaining) = self.sleep_remaining() else {
+            return;
+        };
+        if remaining.is_zero() {
+            self.sleep_deadline = None;
+            self.stop();
+            self.apply_volume();
+            self.set_status_message("Sleep timer: stopped playback");
+        } else if remaining < SLEEP_FADE {
+            if let Some(sink) = &self.sink {
+                let fade = remaining.as_secs_f32() / SLEEP_FADE.as_secs_f32();
+                sink.set_volume(self.volume * self.track_gain * fade);
+            }
+        }
+    }
+
+    /// Counts a play once the current song gets past `PLAY_COUNT_THRESHOLD`
+    /// of its length.
+    pub fn check_play_count(&mut self) {
+        let (Some(path), Some(duration)) = (&self.current_song_path, self.current_song_duration)
+        else {
+            return;
+        };
+        // Each pass through the track counts, including repeats of it
+        if self.elapsed() < duration.mul_f32(PLAY_COUNT_THRESHOLD) {
+            self.play_count_armed = true;
+            return;
+        }
+        if !std::mem::take(&mut self.play_count_armed) {
+            return;
+        }
+        *self.play_counts.entry(path.clone()).or_insert(0) += 1;
+        if let Err(e) = save_play_counts(&self.play_counts) {
+            self.set_status_message(format!("Error saving play counts: {}", e));
+        }
+        if self.sort_mode == SortMode::PlayCount {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Stops playback entirely, unloading the current song and clearing the
+    /// visualizer.
+    pub fn stop(&mut self) {
+        self.reset_playback();
+        self.clear_loop();
+        self.resume_after_queue = None;
+        self.is_playing = false;
+        self.current_song_path = None;
+        self.current_song_duration = None;
+        self.current_metadata = None;
+        self.current_audio_info = None;
+        self.cover_art = None;
+        self.lyrics = None;
+        self.clear_visualizer();
+        self.finish_recording();
+    }
+
+    fn clear_visualizer(&mut self) {
+        self.spectrogram_data.lock().unwrap().fill(SILENCE_DB);
+        self.waveform_data.lock().unwrap().clear();
+        self.spectrogram_history.lock().unwrap().clear();
+        self.channel_levels.lock().unwrap().clear();
+        self.smoothed_bars.fill(SILENCE_DB);
+        self.peak_levels.fill(SILENCE_DB);
+    }
+
+    /// Switches minimal mode, stopping the analysis thread on the way in
+    /// and restarting it for the current song on the way out.
+    pub fn toggle_minimal(&mut self) {
+        self.minimal = !self.minimal;
+        if self.minimal {
+            self.idle_spectrogram_cons = self.join_spectrogram_thread();
+            self.clear_visualizer();
+        } else if let Some(mut cons) = self.idle_spectrogram_cons.take() {
+            // Whatever filled up while stopped is long out of date
+            cons.clear();
+            self.spawn_spectrogram_thread(cons, self.current_channels);
+        }
+    }
+
+    pub fn volume_up(&mut self) {
+        self.unmute();
+        self.set_volume(self.volume + VOLUME_STEP);
+    }
+
+    pub fn volume_down(&mut self) {
+        self.unmute();
+        self.set_volume(self.volume - VOLUME_STEP);
+    }
+
+    pub fn toggle_mute(&mut self) {
+        if self.muted {
+            self.unmute();
+        } else {
+            self.volume_before_mute = self.volume;
+            self.muted = true;
+            self.set_volume(0.0);
+        }
+    }
+
+    fn unmute(&mut self) {
+        if self.muted {
+            self.muted = false;
+            self.set_volume(self.volume_before_mute);
+        }
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        // Round to the step size so repeated adjustments don't drift
+        let volume = (volume / VOLUME_STEP).round() * VOLUME_STEP;
+        self.volume = volume.clamp(0.0, MAX_VOLUME);
+        self.apply_volume();
+    }
+
+    /// Sets the sink to the user's volume scaled by the track's gain.
+    fn apply_volume(&self) {
+        if let Some(sink) = &self.sink {
+            sink.set_volume(self.volume * self.track_gain);
+        }
+    }
+
+    pub fn speed_up(&mut self) {
+        self.set_speed(self.playback_speed + SPEED_STEP);
+    }
+
+    pub fn speed_down(&mut self) {
+        self.set_speed(self.playback_speed - SPEED_STEP);
+    }
+
+    /// Changes playback speed. This resamples, so pitch shifts with tempo.
+    fn set_speed(&mut self, speed: f32) {
+        let speed = (speed / SPEED_STEP).round() * SPEED_STEP;
+        self.playback_speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        if let Some(sink) = &self.sink {
+            sink.set_speed(self.playback_speed);
+        }
+    }
+
+    /// Raises or lowers the selected EQ band; playback picks it up on the
+    /// next frame.
+    fn adjust_eq(&mut self, step_db: f32) {
+        let gain = self.eq_gains.get(self.eq_band);
+        self.eq_gains.set(self.eq_band, (gain + step_db).round());
+        self.show_eq_band();
+    }
+
+    fn show_eq_band(&mut self) {
+        let gain = self.eq_gains.get(self.eq_band);
+        self.set_status_message(format!("EQ {}: {:+} dB", self.eq_band.label(), gain));
+    }
+
+    /// Eases the bars toward the latest spectrum by `bar_smoothing`, then
+    /// applies peak-hold: bars rise with the smoothed level but fall by at
+    /// most `peak_decay` dB per frame.
+    pub fn update_peak_levels(&mut self) {
+        let data = self.spectrogram_data.lock().unwrap();
+        if self.peak_levels.len() != data.len() {
+            self.smoothed_bars = data.clone();
+            self.peak_levels = data.clone();
+            self.needs_redraw = true;
+            return;
+        }
+        for (bar, &level) in self.smoothed_bars.iter_mut().zip(data.iter()) {
+            *bar += (level - *bar) * self.bar_smoothing;
+        }
+        for (peak, &level) in self.peak_levels.iter_mut().zip(self.smoothed_bars.iter()) {
+            let next = level.max(*peak - self.peak_decay);
+            if next != *peak {
+                *peak = next;
+                self.needs_redraw = true;
+            }
+        }
+    }
+
+    /// Playback position of the current song, or zero when nothing is loaded.
+    pub fn elapsed(&self) -> Duration {
+        let samples_per_sec = self.current_sample_rate as u64 * self.current_channels as u64;
+        if self.current_song_path.is_none() || samples_per_sec == 0 {
+            return Duration::ZERO;
+        }
+        let samples = self.samples_played.load(Ordering::Relaxed);
+        Duration::from_secs_f64(samples as f64 / samples_per_sec as f64)
+    }
+
+    pub fn is_seekable(&self) -> bool {
+        self.sink.is_some() && self.current_song_path.is_some()
+    }
+
+    /// Plays the current song again from the start. Reopening the file
+    /// rather than seeking also recovers a song whose decoder got stuck.
+    pub fn restart_song(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(path) = self.current_song_path.clone() else {
+            return Ok(());
+        };
+        self.play_song_by_path(&path)
+    }
+
+    pub fn seek_forward(&mut self, step: Duration) {
+        if self.is_seekable() {
+            self.pending_seek = Some(self.seek_base() + step);
+        } else {
+            self.set_status_message("Nothing is playing");
+        }
+    }
+
+    pub fn seek_backward(&mut self, step: Duration) {
+        if self.is_seekable() {
+            self.pending_seek = Some(self.seek_base().saturating_sub(step));
+        } else {
+            self.set_status_message("Nothing is playing");
+        }
+    }
+
+    /// Where a relative seek counts from: the target of any seek not yet
+    /// done, since the elapsed time only catches up once it is.
+    fn seek_base(&self) -> Duration {
+        let requested = self.seek_request.load(Ordering::SeqCst);
+        self.pending_seek
+            .or((requested != NO_SEEK).then(|| Duration::from_millis(requested)))
+            .unwrap_or_else(|| self.elapsed())
+    }
+
+    /// Sends the seek keys pressed since the last tick as a single seek.
+    pub fn apply_pending_seek(&mut self) {
+        if let Some(target) = self.pending_seek.take() {
+            self.seek_to(target);
+        }
+    }
+
+    /// Asks the decode thread to reposition the current song, clamping to the
+    /// end of the track when its duration is known.
+    pub fn seek_to(&mut self, position: Duration) {
+        let Some(path) = self.current_song_path.clone() else {
+            return;
+        };
+        let position = match self.current_song_duration {
+            Some(total) => position.min(total),
+            None => position,
+        };
+
+        // Once the decoder has reached the end its thread is gone, so
+        // reload the song before seeking back into it
+        let decoder_exited = self.audio_thread_handle.as_ref().is_none_or(|h| h.is_finished());
+        if decoder_exited {
+            let was_playing = self.is_playing;
+            if let Err(e) = self.play_song_by_path(&path) {
+                self.set_status_message(format!("Error seeking: {}", e));
+                return;
+            }
+            if !was_playing {
+                self.play_pause();
+            }
+        }
+
+        self.seek_request.store(position.as_millis() as u64, Ordering::SeqCst);
+        if let Some(handle) = &self.audio_thread_handle {
+            handle.thread().unpark();
+        }
+        #[cfg(feature = "mpris")]
+        {
+            self.seeks = (self.seeks.0 + 1, position);
+        }
+    }
+
+    pub fn mark_loop_start(&mut self) {
+        if !self.is_seekable() {
+            return;
+        }
+        let start = self.elapsed();
+        self.loop_start = Some(start);
+        if self.loop_end.is_some_and(|end| end <= start) {
+            self.loop_end = None;
+        }
+    }
+
+    pub fn mark_loop_end(&mut self) {
+        if !self.is_seekable() {
+            return;
+        }
+        let end = self.elapsed();
+        match self.loop_start {
+            Some(start) if end > start => self.loop_end = Some(end),
+            Some(_) => self.set_status_message("Loop end must be after the start"),
+            None => self.set_status_message("Mark the loop start first"),
+        }
+    }
+
+    pub fn clear_loop(&mut self) {
+        self.loop_start = None;
+        self.loop_end = None;
+    }
+
+    /// The A-B loop, once both ends are marked.
+    pub fn ab_loop(&self) -> Option<(Duration, Duration)> {
+        self.loop_start.zip(self.loop_end)
+    }
+
+    /// Jumps back to the loop start once playback passes the loop end.
+    pub fn check_ab_loop(&mut self) {
+        let Some((start, end)) = self.ab_loop() else {
+            return;
+        };
+        let seek_pending = self.seek_request.load(Ordering::SeqCst) != NO_SEEK;
+        if !seek_pending && self.elapsed() >= end {
+            self.seek_to(start);
+        }
+    }
+
+    /// Parses `seek_input` as `MM:SS`, `H:MM:SS`, plain seconds or a
+    /// percentage like `50%` of the current song.
+    pub fn parsed_seek_target(&self) -> Option<Duration> {
+        parse_seek_target(&self.seek_input, self.current_song_duration)
+    }
+
+    fn submit_seek_input(&mut self) {
+        if let Some(target) = self.parsed_seek_target() {
+            self.seek_to(target);
+        }
+        self.seek_input.clear();
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Runs the typed command line and leaves command mode. Returns true
+    /// when the command asks to quit.
+    fn submit_command(&mut self) -> bool {
+        let line = std::mem::take(&mut self.command_input);
+        self.input_mode = InputMode::Normal;
+        if line.trim().is_empty() {
+            return false;
+        }
+        match parse_command(&line) {
+            Ok(Command::Quit) => return true,
+            Ok(command) => self.run_command(command),
+            Err(e) => self.set_status_message(e),
+        }
+        false
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Goto(n) => {
+                let len = self.active_songs().len();
+                if len == 0 {
+                    return;
+                }
+                self.selected_song_index = Some(n.min(len) - 1);
+            }
+            Command::Sort(mode) => {
+                self.sort_mode = mode;
+                self.sort_playlist();
+            }
+            Command::Repeat(mode) => self.repeat_mode = mode,
+            Command::Shuffle(on) => {
+                if on != Some(self.shuffle) {
+                    self.toggle_shuffle();
+                }
+            }
+            Command::Volume(percent) => {
+                self.unmute();
+                self.set_volume(percent / 100.0);
+            }
+            Command::Speed(speed) => self.set_speed(speed),
+            Command::Seek(target) => {
+                match parse_seek_target(&target, self.current_song_duration) {
+                    Some(target) if self.is_seekable() => self.seek_to(target),
+                    Some(_) => {}
+                    None => self.set_status_message(format!("Invalid seek target: {}", target)),
+                }
+            }
+            Command::Sleep(minutes) => self.set_sleep_timer(minutes),
+            Command::Search(query) => {
+                self.input = query;
+                self.update_search();
+            }
+            Command::Action(action) => self.perform(action),
+            Command::Quit => {}
+        }
+    }
+
+    pub fn play_selected_song(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.sink.is_none() {
+            let reason = self.audio_error.as_deref().unwrap_or("No audio output available");
+            return Err(format!("Cannot play: {}", reason).into());
+        }
+
+        let song_to_play =
+            self.selected_song_index.and_then(|i| self.active_songs().get(i).cloned());
+
+        if let Some(song_path) = song_to_play {
+            self.set_playing_from_queue(false);
+            self.change_song(&song_path)?;
+            self.play_from_active_list();
+        }
+
+        Ok(())
+    }
+
+    /// Empties the sink, then signals the decode and spectrogram threads to
+    /// exit and waits for both.
+    fn reset_playback(&mut self) {
+        self.finish_fade_out();
+        if let Some(preloaded) = self.preloaded.take() {
+            stop_decoder(&preloaded.pipeline.stop, Some(preloaded.pipeline.decode_thread));
+        }
+        if let Some(sink) = &self.sink {
+            sink.stop();
+            sink.clear();
+        }
+
+        stop_decoder(&self.stop_audio_thread, self.audio_thread_handle.take());
+        self.stop_spectrogram();
+        self.song_finished.store(false, Ordering::SeqCst);
+    }
+
+    /// Stops and joins the decode and spectrogram threads and lets the
+    /// library loader exit, then closes audio output, the sinks before the
+    /// stream they play on.
+    pub fn shutdown(&mut self) {
+        self.reset_playback();
+        self.finish_recording();
+        self.library_rx = None;
+        self.sink = None;
+        self.stream_handle = None;
+        self._stream = None;
+    }
+
+    fn stop_spectrogram(&mut self) {
+        self.join_spectrogram_thread();
+        self.idle_spectrogram_cons = None;
+    }
+
+    fn join_spectrogram_thread(&mut self) -> Option<SampleConsumer> {
+        self.stop_spectrogram_thread.store(true, Ordering::SeqCst);
+        self.spectrogram_thread_handle.take().map(|handle| handle.join().unwrap())
+    }
+
+    /// Shows a failed action's error in the status bar.
+    pub fn report_error(&mut self, result: Result<(), Box<dyn Error>>) {
+        if let Err(e) = result {
+            self.set_status_message(e.to_string());
+        }
+    }
+
+    /// Opens a decoder for `path`, remembering whether the song is playable.
+    fn open_song(&mut self, path: &Path) -> Result<SongSource, Box<dyn Error>> {
+        match open_song_source(path) {
+            Ok(source) => {
+                self.unplayable.remove(path);
+                Ok(source)
+            }
+            Err(_) => {
+                self.unplayable.insert(path.to_path_buf());
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                Err(format!("Error: cannot decode {}", name).into())
+            }
+        }
+    }
+
+    fn play_song_by_path(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if self.sink.is_none() {
+            return Ok(());
+        }
+        // Open first so a bad file leaves the current song playing
+        let source = self.open_song(path)?;
+        self.reset_playback();
+        self.start_song(path, source, self.start_fade);
+        Ok(())
+    }
+
+    /// Plays `path` instead of the current song, crossfading into it when
+    /// enabled and something is audible.
+    fn change_song(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        match self.crossfade {
+            Some(duration) if self.is_playing => self.crossfade_to(path, duration),
+            _ => self.play_song_by_path(path),
+        }
+    }
+
+    /// Moves the current song onto its own sink to fade out, and starts
+    /// `path` fading in on a fresh one.
+    fn crossfade_to(&mut self, path: &Path, duration: Duration) -> Result<(), Box<dyn Error>> {
+        let Some(handle) = self.stream_handle.clone() else {
+            return self.play_song_by_path(path);
+        };
+        let source = self.open_song(path)?;
+        let incoming = Sink::try_new(&handle)?;
+
+        // Only one song fades out at a time
+        self.finish_fade_out();
+        self.stop_spectrogram();
+        if let Some(outgoing) = self.sink.replace(incoming) {
+            // Only the incoming song is recorded from here on
+            self.recording_current.store(false, Ordering::SeqCst);
+            self.fading_out = Some(FadingTrack {
+                sink: outgoing,
+                stop: self.stop_audio_thread.clone(),
+                decode_thread: self.audio_thread_handle.take(),
+                started: Instant::now(),
+                duration,
+                gain: self.track_gain,
+            });
+        }
+        self.start_song(path, source, Some(duration));
+        Ok(())
+    }
+
+    /// Ramps down the outgoing song's volume, and drops it once the
+    /// crossfade is over.
+    pub fn update_fade_out(&mut self) {
+        let Some(fade) = &self.fading_out else {
+            return;
+        };
+        let progress = fade.started.elapsed().as_secs_f32() / fade.duration.as_secs_f32();
+        if progress >= 1.0 || fade.sink.empty() {
+            self.finish_fade_out();
+        } else {
+            fade.sink.set_volume(self.volume * fade.gain * (1.0 - progress));
+        }
+    }
+
+    fn finish_fade_out(&mut self) {
+        if let Some(fade) = self.fading_out.take() {
+            fade.sink.stop();
+            stop_decoder(&fade.stop, fade.decode_thread);
+        }
+    }
+
+    /// Starts decoding `source` and queues it on the sink as the current
+    /// song, optionally fading it in.
+    fn start_song(
+        &mut self,
+        path: &Path,
+        source: SongSource,
+        fade_in: Option<Duration>,
+    ) {
+        let (pipeline, source) = self.spawn_pipeline(path, source);
+        self.make_current(pipeline);
+        if let Some(sink) = &self.sink {
+            sink.set_speed(self.playback_speed);
+            match fade_in {
+                Some(duration) => sink.append(source.fade_in(duration)),
+                None => sink.append(source),
+            }
+            sink.play();
+            self.is_playing = true;
+        }
+    }
+
+    /// Spawns the decode thread for `source`, returning its shared state and
+    /// the playback source for the sink.
+    fn spawn_pipeline(
+        &self,
+        path: &Path,
+        source: SongSource,
+    ) -> (SongPipeline, RingBufferSource) {
+        let channels = source.channels();
+        let sample_rate = source.sample_rate();
+        let total_duration = source.total_duration();
+
+        let frames = (sample_rate as f32 * self.buffer_seconds) as usize;
+        let playback_rb = ringbuf::HeapRb::<f32>::new(frames.max(1) * channels.max(1) as usize);
+        let (playback_prod, playback_cons) = playback_rb.split();
+
+        // The analysis thread drains this every tick, so a second is plenty
+        // as long as it holds a full FFT frame
+        let capacity = (sample_rate as usize * channels as usize).max(self.fft_size * 2);
+        let spectrogram_rb = ringbuf::HeapRb::<f32>::new(capacity);
+        let (spectrogram_tap, spectrogram_cons) = spectrogram_rb.split();
+
+        // Fresh per-song state, so an outgoing song's threads keep their own
+        let stop = Arc::new(AtomicBool::new(false));
+        let song_finished = Arc::new(AtomicBool::new(false));
+        let seek_request = Arc::new(AtomicU64::new(NO_SEEK));
+        let flush_playback = Arc::new(AtomicBool::new(false));
+        let samples_played = Arc::new(AtomicU64::new(0));
+        let decoder_waiting = Arc::new(AtomicBool::new(false));
+        let recording = Arc::new(AtomicBool::new(true));
+        let decode_thread = DecodeThread {
+            path: path.to_path_buf(),
+            source,
+            channels,
+            sample_rate,
+            playback_prod,
+            stop: stop.clone(),
+            song_finished: song_finished.clone(),
+            seek_request: seek_request.clone(),
+            flush_playback: flush_playback.clone(),
+            samples_played: samples_played.clone(),
+            waiting: decoder_waiting.clone(),
+            silence_skipper: self.skip_silence.then(|| SilenceSkipper::new(channels, sample_rate)),
+        };
+        let decode_thread = thread::spawn(move || decode_thread.run());
+
+        let source = RingBufferSource {
+            consumer: playback_cons,
+            spectrogram_tap,
+            channels,
+            sample_rate,
+            decode_finished: song_finished.clone(),
+            flush: flush_playback.clone(),
+            samples_played: samples_played.clone(),
+            decoder: decode_thread.thread().clone(),
+            decoder_waiting,
+            equalizer: Equalizer::new(self.eq_gains.clone(), channels, sample_rate),
+            record_tap: self.recorder.tap(channels, sample_rate, recording.clone()),
+        };
+        let pipeline = SongPipeline {
+            path: path.to_path_buf(),
+            stop,
+            song_finished,
+            seek_request,
+            flush_playback,
+            samples_played,
+            recording,
+            decode_thread,
+            spectrogram_cons,
+            channels,
+            sample_rate,
+            total_duration,
+        };
+        (pipeline, source)
+    }
+
+    /// Makes `pipeline` the song that position, seeking and the visualizer
+    /// follow.
+    fn make_current(&mut self, pipeline: SongPipeline) {
+        let is_new_song = self.current_song_path.as_ref() != Some(&pipeline.path);
+        if is_new_song {
+            self.clear_loop();
+            self.play_count_armed = false;
+            self.cover_art = read_cover_art(&pipeline.path);
+            self.lyrics = load_lyrics(&pipeline.path);
+            self.current_audio_info =
+                Some(AudioInfo::new(&pipeline.path, pipeline.sample_rate, pipeline.channels));
+        }
+        self.stop_audio_thread = pipeline.stop;
+        self.song_finished = pipeline.song_finished;
+        self.seek_request = pipeline.seek_request;
+        self.flush_playback = pipeline.flush_playback;
+        self.samples_played = pipeline.samples_played;
+        self.recording_current = pipeline.recording;
+        self.audio_thread_handle = Some(pipeline.decode_thread);
+        self.spawn_spectrogram_thread(pipeline.spectrogram_cons, pipeline.channels);
+
+        self.current_channels = pipeline.channels;
+        self.current_sample_rate = pipeline.sample_rate;
+        self.current_metadata = self
+            .metadata_cache
+            .get(&pipeline.path)
+            .cloned()
+            .or_else(|| read_metadata(&pipeline.path));
+        // Decoders that can't tell the length up front leave it to the
+        // probed metadata
+        self.current_song_duration = pipeline
+            .total_duration
+            .or_else(|| self.current_metadata.as_ref().and_then(|m| m.duration));
+        self.track_gain = match &self.current_metadata {
+            Some(TrackMetadata { replay_gain: Some(db), .. }) if self.normalize => {
+                10f32.powf(db / 20.0)
+            }
+            _ => 1.0,
+        };
+        // Also covers a fresh sink from a crossfade
+        self.apply_volume();
+        self.current_song_path = Some(pipeline.path);
+        if is_new_song {
+            self.notify_now_playing();
+            #[cfg(feature = "scrobble")]
+            self.start_scrobble();
+        }
+    }
+
+    /// Tells Last.fm what's starting to play and remembers it for the
+    /// scrobble. Tracks without artist and title tags are skipped.
+    #[cfg(feature = "scrobble")]
+    fn start_scrobble(&mut self) {
+        self.scrobble_track = None;
+        let (Some(scrobbler), Some(metadata)) = (&self.scrobbler, &self.current_metadata) else {
+            return;
+        };
+        let (Some(artist), Some(title)) = (metadata.artist.clone(), metadata.title.clone()) else {
+            return;
+        };
+        let started_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let track = ScrobbleTrack {
+            artist,
+            title,
+            album: metadata.album.clone(),
+            duration: self.current_song_duration,
+            started_at,
+        };
+        scrobbler.now_playing(track.clone());
+        self.scrobble_track = Some((track, false));
+    }
+
+    /// Scrobbles the current track once it has played past half its length
+    /// or four minutes, as Last.fm asks. Tracks under 30 seconds never count.
+    #[cfg(feature = "scrobble")]
+    pub fn check_scrobble(&mut self) {
+        let elapsed = self.elapsed();
+        let Some(scrobbler) = &self.scrobbler else {
+            return;
+        };
+        let Some((track, scrobbled @ false)) = &mut self.scrobble_track else {
+            return;
+        };
+        let Some(duration) = track.duration.filter(|d| *d > Duration::from_secs(30)) else {
+            return;
+        };
+        let threshold = (duration / 2).min(Duration::from_secs(240));
+        if elapsed >= threshold {
+            scrobbler.scrobble(track.clone());
+            *scrobbled = true;
+        }
+    }
+
+    #[cfg(unix)]
+    fn notify_now_playing(&self) {
+        let (Some(notifier), Some(path)) = (&self.notifier, &self.current_song_path) else {
+            return;
+        };
+        let name = self
+            .current_metadata
+            .as_ref()
+            .and_then(TrackMetadata::display_name)
+            .unwrap_or_else(|| self.display_name(path));
+        let album = self.current_metadata.as_ref().and_then(|m| m.album.clone());
+        let body = match album {
+            Some(album) => format!("{}\n{}", name, album),
+            None => name,
+        };
+        notifier.notify("Now playing".to_string(), body, self.cover_art.clone());
+    }
+
+    #[cfg(not(unix))]
+    fn notify_now_playing(&self) {}
+
+    fn spawn_spectrogram_thread(&mut self, mut spectrogram_cons: SampleConsumer, channels: u16) {
+        if self.minimal {
+            self.idle_spectrogram_cons = Some(spectrogram_cons);
+            return;
+        }
+        self.stop_spectrogram_thread = Arc::new(AtomicBool::new(false));
+        let spectrogram_data = self.spectrogram_data.clone();
+        let waveform_data = self.waveform_data.clone();
+        let spectrogram_history = self.spectrogram_history.clone();
+        let channel_levels = self.channel_levels.clone();
+        let channels = channels.max(1) as usize;
+        let stop_spectrogram_thread = self.stop_spectrogram_thread.clone();
+        let fft_size = self.fft_size;
+        let window = self.fft_window.coefficients(fft_size);
+        // Scales magnitudes so a full-scale sine reads 0 dB whatever the
+        // window's gain
+        let scale = 2.0 / window.iter().sum::<f32>();
+        let spectrogram_thread_handle = thread::spawn(move || {
+            let mut planner = rustfft::FftPlanner::new();
+            let fft = planner.plan_fft_forward(fft_size);
+            let mut buffer: Vec<f32> = Vec::with_capacity(fft_size);
+            // Samples received so far, to tell which channel each belongs to
+            let mut total_received = 0;
+
+            while !stop_spectrogram_thread.load(Ordering::SeqCst) {
+                // Take everything played since the last tick but analyze only
+                // the newest window, so the display never lags behind audio
+                let received = spectrogram_cons.len();
+                buffer.extend(spectrogram_cons.pop_iter());
+                if buffer.len() > fft_size {
+                    buffer.drain(..buffer.len() - fft_size);
+                }
+                total_received += received;
+                let recent = &buffer[buffer.len() - received.min(buffer.len())..];
+                // Interleaved, so the first recent sample's channel follows
+                // from how many came before it
+                let first_channel = (total_received - recent.len()) % channels;
+                let levels = if recent.is_empty() {
+                    Vec::new()
+                } else {
+                    (0..channels)
+                        .map(|channel| {
+                            let offset = (channel + channels - first_channel) % channels;
+                            let samples: Vec<f32> =
+                                recent.iter().skip(offset).step_by(channels).copied().collect();
+                            ChannelLevel::measure(&samples)
+                        })
+                        .collect()
+                };
+                *channel_levels.lock().unwrap() = levels;
+
+                if received > 0 && buffer.len() == fft_size {
+                    let frame = buffer.clone();
+                    let mut complex_buffer: Vec<_> = frame
+                        .iter()
+                        .zip(window.iter())
+                        .map(|(s, w)| rustfft::num_complex::Complex::new(s * w, 0.0))
+                        .collect();
+
+                    fft.process(&mut complex_buffer);
+
+                    let spectrum: Vec<f32> = complex_buffer[..fft_size / 2]
+                        .iter()
+                        .map(|c| (c.norm() * scale).log10() * 20.0)
+                        .map(|v| if v.is_finite() { v.max(SILENCE_DB) } else { SILENCE_DB })
+                        .collect();
+                    let mut history = spectrogram_history.lock().unwrap();
+                    if history.len() == WATERFALL_ROWS {
+                        history.pop_front();
+                    }
+                    history.push_back(spectrum.clone());
+                    drop(history);
+                    *spectrogram_data.lock().unwrap() = spectrum;
+                    *waveform_data.lock().unwrap() = frame;
+                }
+                
+                // Consistent update rate - 30 FPS for smooth visualization
+                thread::sleep(Duration::from_millis(16));
+            }
+            spectrogram_cons
+        });
+        self.spectrogram_thread_handle = Some(spectrogram_thread_handle);
+    }
+
+    /// Tag-based "Artist - Title" for a song, falling back to its filename.
+    pub fn display_name(&self, path: &Path) -> String {
+        self.metadata_cache
+            .get(path)
+            .and_then(TrackMetadata::display_name)
+            .unwrap_or_else(|| path.file_name().unwrap_or_default().to_string_lossy().into_owned())
+    }
+
+    /// Whether the playlist is narrowed down by a search or to favorites.
+    pub fn is_filtered(&self) -> bool {
+        !self.input.is_empty() || self.favorites_only
+    }
+
+    /// The list the user is currently browsing: the selected folder's songs
+    /// in the folder view, the filtered songs while a search or the
+    /// favorites view is active, the full playlist otherwise.
+    pub fn active_songs(&self) -> &[PathBuf] {
+        if self.folder_browser.is_some() {
+            &self.folder_songs
+        } else if self.is_filtered() {
+            &self.search_results
+        } else {
             &self.playlist
+        }
+    }
+
+    /// Switches between the flat playlist and the folder view, keeping the
+    /// selected song selected when it's in the list being switched to.
+    pub fn toggle_folder_view(&mut self) {
+        let selected = self.selected_song_index.and_then(|i| self.active_songs().get(i).cloned());
+        if self.folder_browser.take().is_some() {
+            self.folder_songs.clear();
+            let songs = self.active_songs();
+            let index = selected.and_then(|s| songs.iter().position(|p| *p == s));
+            self.selected_song_index = index.or((!songs.is_empty()).then_some(0));
+        } else {
+            self.folder_browser = Some(FolderBrowser::new(&self.playlist));
+            self.update_folder_songs();
+        }
+    }
+
+    /// Brings the folder view up to date after the playlist changed.
+    fn refresh_folder_view(&mut self) {
+        let Some(browser) = &mut self.folder_browser else {
+            return;
+        };
+        browser.refresh(&self.playlist);
+        self.update_folder_songs();
+    }
+
+    /// Lists the songs of the folder selected in the folder view, keeping
+    /// the track selection if the song is still there.
+    fn update_folder_songs(&mut self) {
+        let Some(browser) = &self.folder_browser else {
+            return;
+        };
+        let selected = self.selected_song_index.and_then(|i| self.folder_songs.get(i).cloned());
+        self.folder_songs =
+            browser.selected_folder().map(|f| songs_in(f, &self.playlist)).unwrap_or_default();
+        let index = selected.and_then(|s| self.folder_songs.iter().position(|p| *p == s));
+        self.selected_song_index = index.or((!self.folder_songs.is_empty()).then_some(0));
+    }
+
+    /// Whether the selection keys move through folders rather than songs.
+    fn folders_focused(&self) -> bool {
+        self.folder_browser.as_ref().is_some_and(|b| b.focus == BrowserFocus::Folders)
+    }
+
+    /// Moves the folder view's folder selection and shows that folder's
+    /// songs.
+    fn move_folder_selection(&mut self, delta: isize) {
+        if let Some(browser) = &mut self.folder_browser {
+            browser.move_selection(delta);
+        }
+        self.update_folder_songs();
+    }
+
+    /// Enter in the folder view: opens the selected folder, or moves to its
+    /// tracks when it has no subfolders. On a track it plays it.
+    fn enter_folder(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(browser) = &mut self.folder_browser else {
+            return Ok(());
+        };
+        if browser.focus == BrowserFocus::Tracks {
+            return self.play_selected_song();
+        }
+        if !browser.enter(&self.playlist) {
+            if !self.folder_songs.is_empty() {
+                browser.focus = BrowserFocus::Tracks;
+            }
+            return Ok(());
+        }
+        self.update_folder_songs();
+        Ok(())
+    }
+
+    /// Backspace in the folder view: from the tracks back to the folders,
+    /// from the folders up to the parent folder.
+    fn leave_folder(&mut self) {
+        let Some(browser) = &mut self.folder_browser else {
+            return;
+        };
+        if browser.focus == BrowserFocus::Tracks {
+            browser.focus = BrowserFocus::Folders;
+        } else if browser.leave(&self.playlist) {
+            self.update_folder_songs();
+        }
+    }
+
+    /// Ctrl+V in the search box: inserts the clipboard's text.
+    pub fn paste_into_search(&mut self) {
+        match paste_from_clipboard() {
+            Ok(text) => self.paste_search_text(&text),
+            Err(e) => self.set_status_message(format!("Couldn't paste: {}", e)),
+        }
+    }
+
+    /// Inserts pasted text at the search cursor, joining multiple lines
+    /// with spaces.
+    pub fn paste_search_text(&mut self, text: &str) {
+        let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+        insert(&mut self.input, &mut self.input_cursor, &lines.join(" "));
+        self.update_search();
+    }
+
+    /// Re-filters `search_results` against the current input, and moves the
+    /// selection to the top.
+    pub fn update_search(&mut self) {
+        self.refilter();
+        self.selected_song_index = if self.active_songs().is_empty() { None } else { Some(0) };
+        // The list's contents changed, so scroll back to the top with the selection
+        *self.playlist_state.offset_mut() = 0;
+    }
+
+    /// Drops the search and the favorites view to show the full playlist
+    /// again, keeping the selected (or else the playing) song selected.
+    pub fn clear_search(&mut self) {
+        self.remember_search();
+        self.favorites_only = false;
+        self.refilter_keeping_selection();
+    }
+
+    /// Clears just the typed search, leaving the favorites view as it is.
+    pub fn clear_search_text(&mut self) {
+        if self.input.is_empty() {
+            return;
+        }
+        self.remember_search();
+        self.refilter_keeping_selection();
+    }
+
+    /// Clears the search text, keeping it for `restore_search`.
+    fn remember_search(&mut self) {
+        if !self.input.is_empty() {
+            self.last_search = Some(std::mem::take(&mut self.input));
+        }
+    }
+
+    /// Brings back the last cleared search and filters by it again.
+    pub fn restore_search(&mut self) {
+        let Some(search) = self.last_search.take() else {
+            self.set_status_message("No search to restore");
+            return;
+        };
+        self.remember_search();
+        self.input = search;
+        self.update_search();
+    }
+
+    /// Like `update_search`, but follows the selected song, or the playing
+    /// one, into the new list instead of jumping back to the top.
+    fn refilter_keeping_selection(&mut self) {
+        let selected = self.selected_song_index.and_then(|i| self.active_songs().get(i).cloned());
+        let keep = selected.or_else(|| self.current_song_path.clone());
+        self.refilter();
+        let songs = self.active_songs();
+        let index = keep.and_then(|path| songs.iter().position(|p| *p == path));
+        self.selected_song_index = index.or(if songs.is_empty() { None } else { Some(0) });
+    }
+
+    pub fn cycle_search_scope(&mut self) {
+        self.search_scope = self.search_scope.next();
+        if !self.input.is_empty() {
+            self.update_search();
+        }
+    }
+
+    /// The text a search in the current scope matches `path` against.
+    fn search_text<'a>(&'a self, path: &'a Path) -> Option<Cow<'a, str>> {
+        let file_name = || path.file_name()?.to_str().map(Cow::Borrowed);
+        match self.search_scope {
+            SearchScope::FileName => file_name(),
+            SearchScope::Path => path.to_str().map(Cow::Borrowed),
+            SearchScope::Tags => {
+                let Some(metadata) = self.metadata_cache.get(path) else {
+                    return file_name();
+                };
+                let tags: Vec<&str> = [&metadata.artist, &metadata.title, &metadata.album]
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect();
+                if tags.is_empty() {
+                    file_name()
+                } else {
+                    Some(Cow::Owned(tags.join(" ")))
+                }
+            }
+        }
+    }
+
+    /// Rebuilds `search_results`: favorites only if that view is on, then
+    /// best fuzzy match in the search scope first.
+    fn refilter(&mut self) {
+        if !self.is_filtered() {
+            self.search_results.clear();
+        } else {
+            let mut scored: Vec<(i64, &PathBuf)> = self
+                .playlist
+                .iter()
+                .filter(|p| !self.favorites_only || self.favorites.contains(*p))
+                .filter_map(|p| {
+                    if self.input.is_empty() {
+                        return Some((0, p));
+                    }
+                    let text = self.search_text(p)?;
+                    fuzzy_score(&self.input, &text).map(|score| (score, p))
+                })
+                .collect();
+            // Stable sort keeps playlist order among equal scores
+            scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+            self.search_results = scored.into_iter().map(|(_, p)| p.clone()).collect();
+        }
+    }
+
+    /// Moves the selection back to the playing song.
+    pub fn select_playing_song(&mut self) {
+        let Some(current) = &self.current_song_path else {
+            self.set_status_message("Nothing is playing");
+            return;
+        };
+        match self.active_songs().iter().position(|p| p == current) {
+            Some(index) => self.selected_song_index = Some(index),
+            None => self.set_status_message("The playing song is hidden by the filter"),
+        }
+    }
+
+    /// Copies the selected song's absolute path to the clipboard.
+    pub fn copy_selected_path(&mut self) {
+        let Some(path) = self.selected_song_index.and_then(|i| self.active_songs().get(i)) else {
+            return;
+        };
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        let text = path.display().to_string();
+        let message = match copy_to_clipboard(&text) {
+            Ok(()) => format!("Copied {}", text),
+            Err(e) => format!("Failed to copy path: {}", e),
+        };
+        self.set_status_message(message);
+    }
+
+    /// Shows the folder holding the selected song in the file manager.
+    pub fn open_selected_folder(&mut self) {
+        let Some(path) = self.selected_song_index.and_then(|i| self.active_songs().get(i)) else {
+            return;
+        };
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let message = match open_in_file_manager(&dir) {
+            Ok(()) => format!("Opened {}", dir.display()),
+            Err(e) => format!("Failed to open {}: {}", dir.display(), e),
+        };
+        self.set_status_message(message);
+    }
+
+    /// Stars or unstars the selected song, saving the change right away.
+    pub fn toggle_favorite(&mut self) {
+        let Some(path) = self.selected_song_index.and_then(|i| self.active_songs().get(i)) else {
+            return;
+        };
+        let path = path.clone();
+        let message = if self.favorites.remove(&path) {
+            "Removed from favorites"
+        } else {
+            self.favorites.insert(path);
+            "Added to favorites"
+        };
+        self.set_status_message(message);
+        if let Err(e) = save_favorites(&self.favorites) {
+            self.set_status_message(format!("Error saving favorites: {}", e));
+        }
+
+        if self.favorites_only {
+            // An unstarred song drops out of the view
+            self.refilter();
+            let len = self.active_songs().len();
+            self.selected_song_index = match self.selected_song_index {
+                Some(i) if len > 0 => Some(i.min(len - 1)),
+                _ => None,
+            };
+        }
+    }
+
+    pub fn toggle_favorites_only(&mut self) {
+        self.favorites_only = !self.favorites_only;
+        self.update_search();
+    }
+
+    /// The songs playback steps through, which stay the same while the
+    /// search or view changes.
+    fn play_songs(&self) -> &[PathBuf] {
+        match &self.play_source {
+            PlaySource::Playlist => &self.playlist,
+            PlaySource::List(songs) => songs,
+        }
+    }
+
+    /// Makes the list on screen the one playback steps through, reshuffling
+    /// if it's a different list.
+    fn play_from_active_list(&mut self) {
+        let source = if self.folder_browser.is_none() && !self.is_filtered() {
+            PlaySource::Playlist
         } else {
-            &self.search_results
+            PlaySource::List(self.active_songs().to_vec())
+        };
+        if source != self.play_source {
+            self.play_source = source;
+            self.rebuild_shuffle_order();
+        }
+    }
+
+    /// Moves the selection to `path` if the list on screen has it.
+    fn select_song(&mut self, path: &Path) {
+        if let Some(index) = self.active_songs().iter().position(|p| p == path) {
+            self.selected_song_index = Some(index);
+        }
+    }
+
+    /// Index of the current song within the list being played.
+    fn current_song_index(&self) -> Option<usize> {
+        let songs = self.play_songs();
+        self.current_song_path
+            .as_ref()
+            .and_then(|p| songs.iter().position(|s| s == p))
+    }
+
+    /// Position of the current song in play order, which is the shuffled
+    /// order when shuffle is on and list order otherwise.
+    fn current_order_position(&self) -> Option<usize> {
+        let current = self.resume_after_queue.as_ref().or(self.current_song_path.as_ref())?;
+        let index = self.play_songs().iter().position(|s| s == current)?;
+        if self.shuffle {
+            self.shuffle_order.iter().position(|&i| i == index)
+        } else {
+            Some(index)
+        }
+    }
+
+    fn index_at_order_position(&self, pos: usize) -> usize {
+        if self.shuffle {
+            self.shuffle_order.get(pos).copied().unwrap_or(pos)
+        } else {
+            pos
+        }
+    }
+
+    pub fn toggle_shuffle(&mut self) {
+        self.shuffle = !self.shuffle;
+        if self.shuffle {
+            self.rebuild_shuffle_order();
+        }
+    }
+
+    /// Regenerates the shuffled play order for the list being played,
+    /// keeping the current song first so every other track plays before
+    /// any repeats.
+    pub fn rebuild_shuffle_order(&mut self) {
+        let mut order: Vec<usize> = (0..self.play_songs().len()).collect();
+        order.shuffle(&mut rand::rng());
+        if let Some(current) = self.current_song_index() {
+            if let Some(pos) = order.iter().position(|&i| i == current) {
+                order.swap(0, pos);
+            }
+        }
+        self.shuffle_order = order;
+    }
+
+    pub fn cycle_repeat_mode(&mut self) {
+        self.repeat_mode = self.repeat_mode.next();
+    }
+
+    /// Picks what to play once the decoder has finished the current track and
+    /// the sink has drained the remaining buffered samples. With crossfade on,
+    /// the next song starts that much before the current one ends instead.
+    pub fn handle_song_end(&mut self) -> Result<(), Box<dyn Error>> {
+        let crossfade_due = self.crossfade.is_some_and(|crossfade| {
+            let elapsed = self.elapsed();
+            self.is_playing
+                && self.ab_loop().is_none()
+                && elapsed >= crossfade
+                && self.current_song_duration.is_some_and(|total| elapsed + crossfade >= total)
+        });
+        if crossfade_due && !self.is_last_song() {
+            self.needs_redraw = true;
+            return self.advance_after_song_end();
+        }
+
+        let Some(sink) = &self.sink else {
+            return Ok(());
+        };
+        if self.preloaded.is_some() && sink.len() <= 1 {
+            // The sink has moved on to the preloaded song
+            self.needs_redraw = true;
+            self.advance_to_preloaded();
+            return Ok(());
+        }
+        let decoded_all = self.song_finished.load(Ordering::SeqCst);
+        if decoded_all && self.crossfade.is_none() && self.preloaded.is_none() && !sink.empty() {
+            if !self.is_last_song() {
+                self.preload_next_song();
+            }
+            return Ok(());
+        }
+
+        let drained = self.sink.as_ref().is_some_and(|s| s.empty());
+        if !drained || !self.song_finished.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.needs_redraw = true;
+
+        if self.is_last_song() {
+            self.is_playing = false;
+            return Ok(());
+        }
+        self.advance_after_song_end()
+    }
+
+    /// Whether playback stops after the current song rather than moving on.
+    fn is_last_song(&self) -> bool {
+        let len = self.play_songs().len();
+        self.queue.is_empty()
+            && self.repeat_mode == RepeatMode::Off
+            && self.current_order_position().is_some_and(|pos| pos + 1 >= len)
+    }
+
+    /// Starts decoding the song that follows the current one and appends it
+    /// to the sink, so it plays as soon as the current one's samples run out.
+    fn preload_next_song(&mut self) {
+        let (path, from_queue) = match self.repeat_mode {
+            RepeatMode::One => match &self.current_song_path {
+                Some(path) => (path.clone(), false),
+                None => return,
+            },
+            RepeatMode::All | RepeatMode::Off => match self.queue.front() {
+                Some(path) => (path.clone(), true),
+                None => match self.next_playable_index() {
+                    Some(index) => (self.play_songs()[index].clone(), false),
+                    None => return,
+                },
+            },
+        };
+        let source = match self.open_song(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                // Playlist songs are left for advance_after_song_end to skip
+                if from_queue {
+                    self.queue.pop_front();
+                }
+                self.set_status_message(e.to_string());
+                return;
+            }
+        };
+        let (pipeline, source) = self.spawn_pipeline(&path, source);
+        if let Some(sink) = &self.sink {
+            sink.append(source);
+        }
+        self.preloaded = Some(PreloadedSong { pipeline, from_queue });
+    }
+
+    /// Switches position tracking and the visualizer over to the preloaded
+    /// song once the sink has started playing it.
+    fn advance_to_preloaded(&mut self) {
+        let Some(preloaded) = self.preloaded.take() else {
+            return;
+        };
+        // The previous song's decoder has already run to the end
+        if let Some(handle) = self.audio_thread_handle.take() {
+            handle.join().unwrap();
+        }
+        self.stop_spectrogram();
+        if preloaded.from_queue {
+            self.queue.pop_front();
+        }
+        self.set_playing_from_queue(preloaded.from_queue);
+        self.make_current(preloaded.pipeline);
+        if let (false, Some(path)) = (preloaded.from_queue, self.current_song_path.clone()) {
+            self.select_song(&path);
+        }
+    }
+
+    fn advance_after_song_end(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.repeat_mode {
+            RepeatMode::One => {
+                if let Some(path) = self.current_song_path.clone() {
+                    self.change_song(&path)?;
+                }
+                Ok(())
+            }
+            RepeatMode::All | RepeatMode::Off => {
+                while let Some(path) = self.queue.pop_front() {
+                    match self.play_from_queue(&path) {
+                        Ok(()) => return Ok(()),
+                        Err(e) => self.set_status_message(e.to_string()),
+                    }
+                }
+                // Skip songs that fail to open. Each failure marks the song
+                // unplayable, so this ends once nothing playable is left.
+                while let Some(index) = self.next_playable_index() {
+                    let path = self.play_songs()[index].clone();
+                    self.set_playing_from_queue(false);
+                    match self.change_song(&path) {
+                        Ok(()) => {
+                            self.select_song(&path);
+                            return Ok(());
+                        }
+                        Err(e) if self.unplayable.contains(&path) => {
+                            self.set_status_message(e.to_string())
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+                // Nothing left to play once the current song runs out
+                self.is_playing = !self.sink.as_ref().is_none_or(|s| s.empty());
+                Ok(())
+            }
+        }
+    }
+
+    /// Like `next_song_index`, but passing over songs already known to be
+    /// unplayable, and stopping at the end of the list unless repeating all.
+    fn next_playable_index(&self) -> Option<usize> {
+        let songs = self.play_songs();
+        let len = songs.len();
+        let start = self.current_order_position();
+        for step in 1..=len {
+            let pos = start.map_or(step - 1, |start| start + step);
+            if pos >= len && self.repeat_mode == RepeatMode::Off {
+                return None;
+            }
+            let index = self.index_at_order_position(pos % len);
+            if !self.unplayable.contains(&songs[index]) {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Index in the list being played of the song after the current one in
+    /// play order, wrapping around at the end.
+    fn next_song_index(&self) -> Option<usize> {
+        let len = self.play_songs().len();
+        if len == 0 {
+            return None;
+        }
+        Some(match self.current_order_position() {
+            Some(pos) => self.index_at_order_position((pos + 1) % len),
+            None => self.index_at_order_position(0),
+        })
+    }
+
+    pub fn next_song(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = self.queue.pop_front() {
+            return self.play_from_queue(&path);
+        }
+        if self.current_song_path.is_none() {
+            self.play_from_active_list();
+        }
+        let Some(next_index) = self.next_song_index() else {
+            return Ok(());
+        };
+
+        let next_path = self.play_songs()[next_index].clone();
+        self.set_playing_from_queue(false);
+        self.change_song(&next_path)?;
+        self.select_song(&next_path);
+
+        Ok(())
+    }
+
+    /// Plays a song taken off the queue, leaving the selection where it is.
+    fn play_from_queue(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.set_playing_from_queue(true);
+        self.change_song(path)
+    }
+
+    /// Tracks where the playlist left off when a queued song is about to
+    /// start, and forgets it once the playlist takes over again.
+    fn set_playing_from_queue(&mut self, from_queue: bool) {
+        if !from_queue {
+            self.resume_after_queue = None;
+        } else if self.resume_after_queue.is_none() {
+            self.resume_after_queue = self.current_song_path.clone();
+        }
+    }
+
+    /// Adds the selected song to the end of the play queue.
+    pub fn enqueue_selected(&mut self) {
+        let Some(path) = self.selected_song_index.and_then(|i| self.active_songs().get(i)) else {
+            return;
+        };
+        let path = path.clone();
+        self.set_status_message(format!("Queued {}", self.display_name(&path)));
+        self.queue.push_back(path);
+    }
+
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+    }
+
+    pub fn previous_song(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.current_song_path.is_none() {
+            self.play_from_active_list();
+        }
+        let len = self.play_songs().len();
+        if len == 0 {
+            return Ok(());
+        }
+
+        let prev_index = match self.current_order_position() {
+            Some(pos) => {
+                if pos == 0 {
+                    self.index_at_order_position(len - 1)
+                } else {
+                    self.index_at_order_position(pos - 1)
+                }
+            }
+            None => self.index_at_order_position(0),
+        };
+
+        let prev_path = self.play_songs()[prev_index].clone();
+        self.set_playing_from_queue(false);
+        self.change_song(&prev_path)?;
+        self.select_song(&prev_path);
+
+        Ok(())
+    }
+
+    /// Removes the selected song from the session's playlist (and search
+    /// results), leaving the file on disk untouched.
+    pub fn remove_selected(&mut self) {
+        let Some(index) = self.selected_song_index else {
+            return;
+        };
+        let Some(path) = self.active_songs().get(index).cloned() else {
+            return;
+        };
+
+        self.playlist.retain(|p| p != &path);
+        self.search_results.retain(|p| p != &path);
+        self.folder_songs.retain(|p| p != &path);
+        if let PlaySource::List(songs) = &mut self.play_source {
+            songs.retain(|p| p != &path);
+        }
+        if self.current_song_path.as_ref() == Some(&path) {
+            self.stop();
+        }
+
+        let len = self.active_songs().len();
+        self.selected_song_index = if len == 0 { None } else { Some(index.min(len - 1)) };
+        self.rebuild_shuffle_order();
+        self.refresh_folder_view();
+    }
+
+    /// Clicking a song selects it, double-clicking plays it, and the scroll
+    /// wheel moves the selection.
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.show_help || self.device_picker.is_some() || self.recent_dirs_picker.is_some() {
+            return;
+        }
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.select_next(),
+            MouseEventKind::ScrollUp => self.select_previous(),
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(fraction) = self.progress_fraction_at(mouse.column, mouse.row) {
+                    if let (true, Some(total)) = (self.is_seekable(), self.current_song_duration) {
+                        self.seek_to(total.mul_f64(fraction));
+                    }
+                    return;
+                }
+                let Some(index) = self.playlist_index_at(mouse.column, mouse.row) else {
+                    return;
+                };
+                self.selected_song_index = Some(index);
+                let double_click = self.last_click.is_some_and(|(last, at)| {
+                    last == index && at.elapsed() < DOUBLE_CLICK_INTERVAL
+                });
+                if double_click {
+                    self.last_click = None;
+                    let result = self.play_selected_song();
+                    self.report_error(result);
+                } else {
+                    self.last_click = Some((index, Instant::now()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// How far along the progress gauge a screen position is, from 0 at its
+    /// left edge to 1 at its right, or `None` outside the gauge.
+    fn progress_fraction_at(&self, column: u16, row: u16) -> Option<f64> {
+        let area = self.progress_area;
+        let inside = column > area.x
+            && column < area.right().saturating_sub(1)
+            && row > area.y
+            && row < area.bottom().saturating_sub(1);
+        if !inside {
+            return None;
+        }
+        let width = area.width.saturating_sub(2).max(1);
+        Some(f64::from(column - area.x - 1) / f64::from(width))
+    }
+
+    /// Song index under a screen position inside the playlist's borders.
+    fn playlist_index_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.playlist_area;
+        let inside = column > area.x
+            && column < area.right().saturating_sub(1)
+            && row > area.y
+            && row < area.bottom().saturating_sub(1);
+        if !inside {
+            return None;
+        }
+        let index = self.playlist_state.offset() + (row - area.y - 1) as usize;
+        (index < self.active_songs().len()).then_some(index)
+    }
+
+    /// Applies requests from desktop media controls and publishes the
+    /// current state back to them.
+    #[cfg(feature = "mpris")]
+    pub fn poll_mpris(&mut self) {
+        let Some(mpris) = &self.mpris else {
+            return;
         };
+        let commands: Vec<MediaCommand> = mpris.commands.try_iter().collect();
+        *mpris.now_playing.lock().unwrap() = self.now_playing();
+        for command in commands {
+            self.run_media_command(command);
+            self.needs_redraw = true;
+        }
+    }
+
+    #[cfg(feature = "mpris")]
+    fn now_playing(&self) -> NowPlaying {
+        let metadata = self.current_metadata.as_ref();
+        NowPlaying {
+            path: self.current_song_path.clone(),
+            playing: self.is_playing,
+            title: metadata.and_then(|m| m.title.clone()),
+            artist: metadata.and_then(|m| m.artist.clone()),
+            album: metadata.and_then(|m| m.album.clone()),
+            length: self.current_song_duration,
+            position: self.elapsed(),
+            volume: self.volume as f64,
+            rate: self.playback_speed as f64,
+            min_rate: MIN_SPEED as f64,
+            max_rate: MAX_SPEED as f64,
+            shuffle: self.shuffle,
+            repeat: Some(self.repeat_mode),
+            seekable: self.is_seekable(),
+            seeks: self.seeks,
+        }
+    }
+
+    #[cfg(feature = "mpris")]
+    fn run_media_command(&mut self, command: MediaCommand) {
+        let micros = |n: i64| Duration::from_micros(n.unsigned_abs());
+        match command {
+            MediaCommand::PlayPause => self.play_pause(),
+            MediaCommand::Play if !self.is_playing => self.play_pause(),
+            MediaCommand::Pause if self.is_playing => self.play_pause(),
+            MediaCommand::Play | MediaCommand::Pause => {}
+            MediaCommand::Stop => self.stop(),
+            MediaCommand::Next => {
+                let result = self.next_song();
+                self.report_error(result);
+            }
+            MediaCommand::Previous => {
+                let result = self.previous_song();
+                self.report_error(result);
+            }
+            MediaCommand::Seek(offset) if self.is_seekable() => {
+                let target = if offset < 0 {
+                    self.elapsed().saturating_sub(micros(offset))
+                } else {
+                    self.elapsed() + micros(offset)
+                };
+                self.seek_to(target);
+            }
+            MediaCommand::SetPosition(position) if self.is_seekable() && position >= 0 => {
+                self.seek_to(micros(position));
+            }
+            MediaCommand::Seek(_) | MediaCommand::SetPosition(_) => {}
+            MediaCommand::SetVolume(volume) => {
+                self.unmute();
+                self.set_volume(volume as f32);
+            }
+            MediaCommand::SetShuffle(on) => {
+                if on != self.shuffle {
+                    self.toggle_shuffle();
+                }
+            }
+            MediaCommand::SetRepeat(mode) => self.repeat_mode = mode,
+        }
+    }
+
+    /// Why `action` would do nothing right now, so the key press can say so
+    /// instead of being silently ignored.
+    fn no_effect_reason(&self, action: Action) -> Option<&'static str> {
+        let songs = self.active_songs();
+        let selected = self.selected_song_index.and_then(|i| songs.get(i));
+        let no_selection =
+            if songs.is_empty() { "The playlist is empty" } else { "No song selected" };
+        let nothing_loaded = self.current_song_path.is_none();
+        match action {
+            Action::PlaySelected
+            | Action::ToggleFavorite
+            | Action::Enqueue
+            | Action::RemoveSelected
+            | Action::CopyPath
+            | Action::OpenFolder
+                if selected.is_none() =>
+            {
+                Some(no_selection)
+            }
+            Action::PlayPause if nothing_loaded && selected.is_none() => Some(no_selection),
+            Action::Next if self.queue.is_empty() && songs.is_empty() => {
+                Some("The playlist is empty")
+            }
+            Action::Previous if songs.is_empty() => Some("The playlist is empty"),
+            Action::Stop | Action::RestartSong if nothing_loaded => Some("Nothing is playing"),
+            Action::JumpToTime | Action::MarkLoopStart | Action::MarkLoopEnd
+                if !self.is_seekable() =>
+            {
+                Some("Nothing is playing")
+            }
+            Action::ClearLoop if self.loop_start.is_none() => Some("No loop to clear"),
+            Action::ClearQueue if self.queue.is_empty() => Some("The queue is already empty"),
+            _ => None,
+        }
+    }
+
+    /// Carries out a key-bound action. Quitting is left to `run_app`.
+    pub fn perform(&mut self, action: Action) {
+        if let Some(reason) = self.no_effect_reason(action) {
+            self.set_status_message(reason);
+            return;
+        }
+        match action {
+            Action::PlaySelected => {
+                let result = self.play_selected_song();
+                self.report_error(result);
+            }
+            Action::PlayPause => self.play_pause(),
+            Action::Stop => self.stop(),
+            Action::Next => {
+                let result = self.next_song();
+                self.report_error(result);
+            }
+            Action::Previous => {
+                let result = self.previous_song();
+                self.report_error(result);
+            }
+            Action::RestartSong => {
+                let result = self.restart_song();
+                self.report_error(result);
+            }
+            Action::SelectDown if self.folders_focused() => self.move_folder_selection(1),
+            Action::SelectUp if self.folders_focused() => self.move_folder_selection(-1),
+            Action::PageDown if self.folders_focused() => self.move_folder_selection(self.page()),
+            Action::PageUp if self.folders_focused() => self.move_folder_selection(-self.page()),
+            Action::SelectFirst if self.folders_focused() => self.move_folder_selection(isize::MIN),
+            Action::SelectLast if self.folders_focused() => self.move_folder_selection(isize::MAX),
+            Action::SelectDown => self.select_next(),
+            Action::SelectUp => self.select_previous(),
+            Action::PageDown => self.select_page(true),
+            Action::PageUp => self.select_page(false),
+            Action::SelectFirst => self.select_first(),
+            Action::SelectLast => self.select_last(),
+            Action::ToggleFolderView => self.toggle_folder_view(),
+            Action::EnterFolder => {
+                let result = self.enter_folder();
+                self.report_error(result);
+            }
+            Action::LeaveFolder => self.leave_folder(),
+            Action::JumpToPlaying => self.select_playing_song(),
+            Action::SeekBackward => self.seek_backward(self.seek_step),
+            Action::SeekForward => self.seek_forward(self.seek_step),
+            Action::JumpToTime => self.input_mode = InputMode::Seeking,
+            Action::ToggleRemaining => self.show_remaining = !self.show_remaining,
+            Action::VolumeUp => self.volume_up(),
+            Action::VolumeDown => self.volume_down(),
+            Action::ToggleMute => self.toggle_mute(),
+            Action::SpeedDown => self.speed_down(),
+            Action::SpeedUp => self.speed_up(),
+            Action::MarkLoopStart => self.mark_loop_start(),
+            Action::MarkLoopEnd => self.mark_loop_end(),
+            Action::ClearLoop => self.clear_loop(),
+            Action::CycleRepeat => self.cycle_repeat_mode(),
+            Action::ToggleShuffle => self.toggle_shuffle(),
+            Action::CycleSort => self.cycle_sort_mode(),
+            Action::ToggleVisualizer => self.visualizer_mode = self.visualizer_mode.next(),
+            Action::ToggleMinimal => self.toggle_minimal(),
+            Action::EqNextBand => {
+                self.eq_band = self.eq_band.next();
+                self.show_eq_band();
+            }
+            Action::EqUp => self.adjust_eq(GAIN_STEP_DB),
+            Action::EqDown => self.adjust_eq(-GAIN_STEP_DB),
+            Action::Search => {
+                // Searching covers the whole playlist
+                if self.folder_browser.is_some() {
+                    self.toggle_folder_view();
+                }
+                self.input_mode = InputMode::Editing;
+                self.input_cursor = self.input.len();
+            }
+            Action::ClearSearch => self.clear_search(),
+            Action::ClearSearchText => self.clear_search_text(),
+            Action::RestoreSearch => self.restore_search(),
+            Action::CycleSearchScope => self.cycle_search_scope(),
+            Action::ToggleFavorite => self.toggle_favorite(),
+            Action::FavoritesOnly => self.toggle_favorites_only(),
+            Action::RemoveSelected => self.remove_selected(),
+            Action::Enqueue => self.enqueue_selected(),
+            Action::ClearQueue => self.clear_queue(),
+            Action::ExportPlaylist => self.export_playlist(),
+            Action::ToggleRecording => self.toggle_recording(),
+            Action::CopyPath => self.copy_selected_path(),
+            Action::OpenFolder => self.open_selected_folder(),
+            Action::ChooseDevice => self.open_device_picker(),
+            Action::RecentDirs => self.open_recent_dirs_picker(),
+            Action::SleepTimer => self.cycle_sleep_timer(),
+            Action::CommandMode => {
+                self.input_mode = InputMode::Command;
+                self.command_cursor = self.command_input.len();
+            }
+            Action::AddPath => {
+                self.input_mode = InputMode::AddingPath;
+                self.path_cursor = self.path_input.len();
+            }
+            Action::Help => self.show_help = true,
+            Action::Quit => {}
+        }
+    }
 
-        let len = songs_to_play.len();
+    pub fn select_next(&mut self) {
+        let len = self.active_songs().len();
         if len == 0 {
             return;
         }
@@ -351,13 +3272,7 @@ impl App {
     }
 
     pub fn select_previous(&mut self) {
-        let songs_to_play = if self.input.is_empty() {
-            &self.playlist
-        } else {
-            &self.search_results
-        };
-
-        let len = songs_to_play.len();
+        let len = self.active_songs().len();
         if len == 0 {
             return;
         }
@@ -367,69 +3282,237 @@ impl App {
             .map_or(0, |i| if i == 0 { len - 1 } else { i - 1 });
         self.selected_song_index = Some(i);
     }
+
+    /// Moves the selection by a screenful of rows, stopping at the ends.
+    pub fn select_page(&mut self, down: bool) {
+        let len = self.active_songs().len();
+        if len == 0 {
+            return;
+        }
+        let page = self.page() as usize;
+        let i = match self.selected_song_index {
+            Some(i) if down => (i + page).min(len - 1),
+            Some(i) => i.saturating_sub(page),
+            None => 0,
+        };
+        self.selected_song_index = Some(i);
+    }
+
+    /// Rows inside the playlist's borders.
+    fn page(&self) -> isize {
+        (self.playlist_area.height.saturating_sub(2) as isize).max(1)
+    }
+
+    pub fn select_first(&mut self) {
+        if !self.active_songs().is_empty() {
+            self.selected_song_index = Some(0);
+        }
+    }
+
+    pub fn select_last(&mut self) {
+        let len = self.active_songs().len();
+        if len > 0 {
+            self.selected_song_index = Some(len - 1);
+        }
+    }
 }
 
 pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
     let tick_rate = Duration::from_millis(16); // ~60 FPS for smooth UI
-    
-    loop {
-        // Non-blocking event poll with short timeout
-        if event::poll(tick_rate)? {
-            if let Event::Key(key) = event::read()? {
+    let idle_tick_rate = Duration::from_millis(250);
+    // Counts ticks while playing, for the status bar's animation
+    let mut tick: u64 = 0;
+
+    'run: loop {
+        // Poll quickly while something is animating, slowly when idle
+        let mut timeout = if app.is_playing || app.is_library_loading() || app.needs_redraw {
+            tick_rate
+        } else {
+            idle_tick_rate
+        };
+        // Handle everything already queued before the tick, so a held key's
+        // repeats land in the same tick
+        while event::poll(timeout)? {
+            timeout = Duration::ZERO;
+            let event = event::read()?;
+            app.needs_redraw = true;
+            if let Event::Mouse(mouse) = event {
+                app.handle_mouse(mouse);
+            }
+            // The terminal's own paste shortcut arrives as one bracketed paste
+            if let Event::Paste(text) = &event {
+                if matches!(app.input_mode, InputMode::Editing) {
+                    app.paste_search_text(text);
+                }
+            }
+            if let Event::Key(key) = event {
+                if app.show_help {
+                    // Any key dismisses the help overlay
+                    app.show_help = false;
+                    continue;
+                }
+                if app.device_picker.is_some() {
+                    app.handle_device_picker_key(key.code);
+                    continue;
+                }
+                if app.recent_dirs_picker.is_some() {
+                    app.handle_recent_dirs_key(key.code);
+                    continue;
+                }
+                let shift = key.modifiers.contains(KeyModifiers::SHIFT);
                 match app.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char('e') => app.input_mode = InputMode::Editing,
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('p') => app.play_pause(),
-                        KeyCode::Char('n') => {
-                            let _ = app.next_song();
+                    InputMode::Normal => match app.keymap.action(key.code) {
+                        Some(Action::Quit) if app.confirm_quit && app.is_playing => {
+                            app.input_mode = InputMode::ConfirmingQuit;
                         }
-                        KeyCode::Char('b') => {
-                            let _ = app.previous_song();
+                        Some(Action::Quit) => {
+                            break 'run;
                         }
-                        KeyCode::Char(' ') => {
-                            let _ = app.play_selected_song();
+                        Some(Action::SeekBackward) if shift => app.seek_backward(LONG_SEEK_STEP),
+                        Some(Action::SeekForward) if shift => app.seek_forward(LONG_SEEK_STEP),
+                        Some(action) => app.perform(action),
+                        None => {}
+                    },
+                    InputMode::Editing => match key.code {
+                        KeyCode::Enter => app.input_mode = InputMode::Normal,
+                        KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.paste_into_search()
                         }
-                        KeyCode::Down => app.select_next(),
-                        KeyCode::Up => app.select_previous(),
-                        KeyCode::Left => app.seek_backward(),
-                        KeyCode::Right => app.seek_forward(),
-                        KeyCode::Char('c') => {
-                            app.input.clear();
-                            app.search_results.clear();
-                            app.selected_song_index =
-                                if app.playlist.is_empty() { None } else { Some(0) };
+                        // Typing stays in the search box, so pausing needs a combo
+                        KeyCode::Char(' ') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.perform(Action::PlayPause)
+                        }
+                        KeyCode::Esc => app.input_mode = InputMode::Normal,
+                        KeyCode::Tab => app.cycle_search_scope(),
+                        _ => {
+                            let edit = apply_key(&mut app.input, &mut app.input_cursor, key);
+                            if edit == Edit::Changed {
+                                app.update_search();
+                            }
                         }
-                        _ => {}
                     },
-                    InputMode::Editing => match key.code {
-                        KeyCode::Enter => {
-                            app.search_results = app
-                                .playlist
-                                .iter()
-                                .filter(|p| {
-                                    p.to_str()
-                                        .unwrap_or("")
-                                        .to_lowercase()
-                                        .contains(&app.input.to_lowercase())
-                                })
-                                .cloned()
-                                .collect();
-                            app.selected_song_index = app.search_results.get(0).map(|_| 0);
+                    InputMode::Command => match key.code {
+                        KeyCode::Enter if app.submit_command() => {
+                            break 'run;
+                        }
+                        KeyCode::Esc => {
+                            app.command_input.clear();
+                            app.input_mode = InputMode::Normal;
+                        }
+                        _ => {
+                            apply_key(&mut app.command_input, &mut app.command_cursor, key);
+                        }
+                    },
+                    InputMode::AddingPath => match key.code {
+                        KeyCode::Enter => app.submit_add_path(),
+                        KeyCode::Esc => {
+                            app.path_input.clear();
                             app.input_mode = InputMode::Normal;
                         }
-                        KeyCode::Char(c) => app.input.push(c),
+                        _ => {
+                            apply_key(&mut app.path_input, &mut app.path_cursor, key);
+                        }
+                    },
+                    InputMode::ConfirmingQuit => match key.code {
+                        KeyCode::Char('y') | KeyCode::Char('Y') => {
+                            break 'run;
+                        }
+                        _ => app.input_mode = InputMode::Normal,
+                    },
+                    InputMode::Seeking => match key.code {
+                        KeyCode::Enter => app.submit_seek_input(),
+                        KeyCode::Char(c) if c.is_ascii_digit() || c == ':' || c == '%' => {
+                            app.seek_input.push(c)
+                        }
                         KeyCode::Backspace => {
-                            app.input.pop();
+                            app.seek_input.pop();
+                        }
+                        KeyCode::Esc => {
+                            app.seek_input.clear();
+                            app.input_mode = InputMode::Normal;
                         }
-                        KeyCode::Esc => app.input_mode = InputMode::Normal,
                         _ => {}
                     },
                 }
             }
         }
         
-        // Always redraw the UI at consistent intervals for smooth visualizer
-        terminal.draw(|f| ui(f, &mut app))?;
+        app.apply_pending_seek();
+        app.poll_library();
+        app.check_ab_loop();
+        app.check_sleep_timer();
+        app.check_background_errors();
+        app.check_play_count();
+        app.check_output_device();
+        #[cfg(feature = "mpris")]
+        app.poll_mpris();
+        #[cfg(feature = "scrobble")]
+        app.check_scrobble();
+        let result = app.handle_song_end();
+        app.report_error(result);
+        app.update_fade_out();
+        app.update_peak_levels();
+        app.expire_status_message();
+
+        // Redraw every tick while playing for a smooth visualizer and
+        // progress bar; otherwise only when something changed. Without the
+        // visualizer only the elapsed time moves.
+        if app.is_playing {
+            tick = tick.wrapping_add(1);
+        }
+        let second = app.elapsed().as_secs();
+        let animating = app.is_playing && (!app.minimal || second != app.drawn_second);
+        if animating || app.needs_redraw {
+            terminal.draw(|f| ui(f, &mut app, tick))?;
+            app.needs_redraw = false;
+            app.drawn_second = second;
+            draw_album_art(terminal, &mut app, tick)?;
+        }
+    }
+
+    // The terminal is still in use, so failures are printed once main has
+    // restored it
+    let saved = app.save_session().and(app.save_settings());
+    app.shutdown();
+    saved
+}
+
+/// Draws the cover over its panel with the terminal's graphics protocol.
+/// Images persist between frames, so this only writes when the song or the
+/// panel changes.
+fn draw_album_art<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    tick: u64,
+) -> io::Result<()> {
+    let Some(protocol) = app.image_protocol else {
+        return Ok(());
+    };
+    // Popups are drawn over the panel, so the image steps aside for them
+    let covered =
+        app.show_help || app.device_picker.is_some() || app.recent_dirs_picker.is_some();
+    let area = app.album_art_area;
+    let wanted = match (&app.cover_art, &app.current_song_path) {
+        (Some(art), Some(path)) if can_display(protocol, art) && !covered && !area.is_empty() => {
+            Some((path.clone(), area))
+        }
+        _ => None,
+    };
+    if wanted == app.drawn_art {
+        return Ok(());
+    }
+
+    let mut stdout = io::stdout();
+    if app.drawn_art.take().is_some() {
+        write!(stdout, "{}", clear_escape(protocol))?;
+        // Repaint the cells the old image was covering
+        terminal.clear()?;
+        terminal.draw(|f| ui(f, app, tick))?;
+    }
+    if let (Some(art), Some((_, area))) = (&app.cover_art, &wanted) {
+        queue!(stdout, MoveTo(area.x, area.y), Print(image_escape(protocol, art, *area)))?;
     }
+    stdout.flush()?;
+    app.drawn_art = wanted;
+    Ok(())
 }
@@ -0,0 +1,157 @@
+// src/browser.rs
+use ratatui::widgets::ListState;
+use std::path::{Path, PathBuf};
+
+/// Which pane of the folder view the selection keys move in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BrowserFocus {
+    Folders,
+    Tracks,
+}
+
+/// The folder view's position in the library's directory tree. The tree
+/// itself is read off the playlist's paths, so it always matches what was
+/// scanned.
+pub struct FolderBrowser {
+    /// The deepest folder holding every song; `leave` stops here.
+    pub root: PathBuf,
+    /// The folder whose contents are listed on the left.
+    pub current: PathBuf,
+    /// `current` itself when it holds songs directly, then its subfolders
+    /// that hold songs somewhere below.
+    pub entries: Vec<PathBuf>,
+    pub state: ListState,
+    pub focus: BrowserFocus,
+}
+
+impl FolderBrowser {
+    pub fn new(songs: &[PathBuf]) -> FolderBrowser {
+        let root = common_folder(songs);
+        let mut browser = FolderBrowser {
+            current: root.clone(),
+            root,
+            entries: Vec::new(),
+            state: ListState::default(),
+            focus: BrowserFocus::Folders,
+        };
+        browser.refresh(songs);
+        browser
+    }
+
+    /// Rebuilds the folder list after the playlist changed, keeping the
+    /// selection on the same folder where possible.
+    pub fn refresh(&mut self, songs: &[PathBuf]) {
+        let root = common_folder(songs);
+        if !self.current.starts_with(&root) {
+            self.current = root.clone();
+        }
+        self.root = root;
+        let selected = self.selected_folder().map(Path::to_path_buf);
+        self.entries = list_entries(&self.current, songs);
+        let index = selected.and_then(|s| self.entries.iter().position(|e| *e == s));
+        self.state.select(index.or((!self.entries.is_empty()).then_some(0)));
+    }
+
+    pub fn selected_folder(&self) -> Option<&Path> {
+        self.state.selected().and_then(|i| self.entries.get(i)).map(PathBuf::as_path)
+    }
+
+    /// Moves the folder selection by `delta`, stopping at the ends.
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as isize;
+        let last = self.entries.len() as isize - 1;
+        self.state.select(Some(current.saturating_add(delta).clamp(0, last) as usize));
+    }
+
+    /// Opens the selected folder. Returns false when it has no subfolders
+    /// to show, so its tracks should take the focus instead.
+    pub fn enter(&mut self, songs: &[PathBuf]) -> bool {
+        let Some(folder) = self.selected_folder().map(Path::to_path_buf) else {
+            return false;
+        };
+        if folder == self.current {
+            return false;
+        }
+        let entries = list_entries(&folder, songs);
+        if entries.iter().all(|entry| *entry == folder) {
+            return false;
+        }
+        self.current = folder;
+        self.entries = entries;
+        self.state.select(Some(0));
+        true
+    }
+
+    /// Goes up to the parent folder, selecting the one just left. Returns
+    /// false at the root.
+    pub fn leave(&mut self, songs: &[PathBuf]) -> bool {
+        if self.current == self.root {
+            return false;
+        }
+        let Some(parent) = self.current.parent().map(Path::to_path_buf) else {
+            return false;
+        };
+        let left = std::mem::replace(&mut self.current, parent);
+        self.entries = list_entries(&self.current, songs);
+        let index = self.entries.iter().position(|entry| *entry == left);
+        self.state.select(index.or(Some(0)));
+        true
+    }
+
+    /// How an entry is shown: `.` for the current folder, otherwise the
+    /// subfolder's name.
+    pub fn entry_name(&self, entry: &Path) -> String {
+        if entry == self.current {
+            return ".".to_string();
+        }
+        entry.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+}
+
+/// Songs directly inside `folder`, in playlist order.
+pub fn songs_in(folder: &Path, songs: &[PathBuf]) -> Vec<PathBuf> {
+    songs.iter().filter(|song| song.parent() == Some(folder)).cloned().collect()
+}
+
+fn list_entries(folder: &Path, songs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut subfolders = Vec::new();
+    let mut has_own_songs = false;
+    for song in songs {
+        let Ok(rest) = song.strip_prefix(folder) else {
+            continue;
+        };
+        let mut components = rest.components();
+        match (components.next(), components.next()) {
+            (Some(_), None) => has_own_songs = true,
+            (Some(child), Some(_)) => subfolders.push(folder.join(child)),
+            _ => {}
+        }
+    }
+    subfolders.sort();
+    subfolders.dedup();
+    subfolders.sort_by_cached_key(|f| f.to_string_lossy().to_lowercase());
+    if has_own_songs {
+        subfolders.insert(0, folder.to_path_buf());
+    }
+    subfolders
+}
+
+/// The deepest folder that every song is somewhere under.
+fn common_folder(songs: &[PathBuf]) -> PathBuf {
+    let mut folders = songs.iter().filter_map(|song| song.parent());
+    let Some(first) = folders.next() else {
+        return PathBuf::new();
+    };
+    let mut common = first.to_path_buf();
+    for folder in folders {
+        while !folder.starts_with(&common) {
+            if !common.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+    common
+}
@@ -0,0 +1,94 @@
+// src/command.rs
+use crate::{
+    app::{RepeatMode, SortMode},
+    keymap::Action,
+};
+
+/// The longest sleep timer `:sleep` accepts, a day.
+const MAX_SLEEP_MINUTES: u64 = 24 * 60;
+
+/// A line typed after `:`.
+pub enum Command {
+    /// Select the Nth song (1-based) in the visible list.
+    Goto(usize),
+    Sort(SortMode),
+    Repeat(RepeatMode),
+    /// `None` toggles.
+    Shuffle(Option<bool>),
+    /// Volume in percent.
+    Volume(f32),
+    Speed(f32),
+    /// A seek target in the same forms the jump prompt accepts.
+    Seek(String),
+    /// Minutes until playback stops; `None` cancels the timer.
+    Sleep(Option<u64>),
+    Search(String),
+    /// Any key-bindable action, by its config name.
+    Action(Action),
+    Quit,
+}
+
+/// Parses a command line such as `goto 50`, `sort duration` or `repeat all`.
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim();
+    let (name, arg) = match line.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (line, ""),
+    };
+    let command = match name {
+        "goto" | "g" => Command::Goto(
+            arg.parse().ok().filter(|&n| n > 0).ok_or("Usage: goto <song number>")?,
+        ),
+        "sort" => Command::Sort(match arg {
+            "name" => SortMode::Name,
+            "modified" | "date" => SortMode::Modified,
+            "duration" | "length" => SortMode::Duration,
+            "plays" | "played" => SortMode::PlayCount,
+            _ => return Err("Usage: sort name|modified|duration|plays".to_string()),
+        }),
+        "repeat" => Command::Repeat(match arg {
+            "off" => RepeatMode::Off,
+            "one" => RepeatMode::One,
+            "all" => RepeatMode::All,
+            _ => return Err("Usage: repeat off|one|all".to_string()),
+        }),
+        "shuffle" => Command::Shuffle(match arg {
+            "" => None,
+            "on" => Some(true),
+            "off" => Some(false),
+            _ => return Err("Usage: shuffle [on|off]".to_string()),
+        }),
+        "volume" | "vol" => Command::Volume(
+            arg.trim_end_matches('%')
+                .parse()
+                .ok()
+                .filter(|v: &f32| v.is_finite())
+                .ok_or("Usage: volume <percent>")?,
+        ),
+        "speed" => Command::Speed(
+            arg.trim_end_matches('x')
+                .parse()
+                .ok()
+                .filter(|s: &f32| s.is_finite())
+                .ok_or("Usage: speed <factor>")?,
+        ),
+        "seek" if !arg.is_empty() => Command::Seek(arg.to_string()),
+        "seek" => return Err("Usage: seek MM:SS|N%".to_string()),
+        "sleep" => Command::Sleep(match arg {
+            "off" => None,
+            _ => Some(
+                arg.parse()
+                    .ok()
+                    .filter(|m| (1..=MAX_SLEEP_MINUTES).contains(m))
+                    .ok_or("Usage: sleep <1-1440 minutes>|off")?,
+            ),
+        }),
+        "search" | "find" => Command::Search(arg.to_string()),
+        "q" | "quit" => Command::Quit,
+        _ => match Action::from_name(name) {
+            Some(action) if arg.is_empty() => Command::Action(action),
+            _ => return Err(format!("Unknown command: {}", line)),
+        },
+    };
+    Ok(command)
+}
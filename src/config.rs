@@ -0,0 +1,347 @@
+// src/config.rs
+use crate::{
+    app::FftWindow,
+    keymap::{parse_key, Action, Keymap},
+    theme::Theme,
+};
+#[cfg(feature = "scrobble")]
+use crate::scrobbler::LastfmCredentials;
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const CONFIG_DIR_NAME: &str = "simple_music_player";
+const CONFIG_FILE_NAME: &str = "config.toml";
+/// Allowed range for `fft_size`.
+const MIN_FFT_SIZE: usize = 64;
+const MAX_FFT_SIZE: usize = 32768;
+/// Allowed range for `buffer_seconds`: below the minimum a busy system
+/// can't refill the buffer in time.
+const MIN_BUFFER_SECONDS: f32 = 0.2;
+const MAX_BUFFER_SECONDS: f32 = 60.0;
+/// Highest `default_volume`, in percent, matching the volume keys' limit.
+const MAX_DEFAULT_VOLUME: f32 = 200.0;
+/// Used when neither the config nor the environment names a music folder.
+const FALLBACK_MUSIC_DIR: &str = "music";
+
+/// User settings read from `$XDG_CONFIG_HOME/simple_music_player/config.toml`
+/// (`~/.config` by default).
+///
+/// Unknown keys are an error, so a misspelled setting doesn't go unnoticed.
+pub struct Config {
+    /// Folders scanned for songs, set with `music_dirs = ["~/Music", …]` or
+    /// a single `music_dir`.
+    pub music_dirs: Vec<PathBuf>,
+    /// An m3u/m3u8 playlist to open instead of scanning `music_dirs`.
+    pub playlist_file: Option<PathBuf>,
+    /// A song given on the command line, played as soon as the player
+    /// starts.
+    pub play_on_start: Option<PathBuf>,
+    /// Name (or part of the name) of the audio output device to use.
+    pub output_device: Option<String>,
+    /// Reopen the song and position saved by the previous session.
+    pub resume_on_start: bool,
+    /// How far visualizer bars fall per frame, in dB.
+    pub peak_decay: f32,
+    /// How quickly visualizer bars follow the spectrum, from just above 0
+    /// (very smooth) to 1 (no smoothing).
+    pub bar_smoothing: f32,
+    /// Samples per spectrum frame, a power of two: larger gives finer
+    /// frequency detail but reacts more slowly.
+    pub fft_size: usize,
+    pub fft_window: FftWindow,
+    /// Decoded audio queued ahead of playback, in seconds: more guards
+    /// against stutter on a busy system, less saves memory.
+    pub buffer_seconds: f32,
+    /// How far the seek keys jump, in seconds.
+    pub seek_step_secs: u64,
+    /// Start with the visualizer hidden and audio analysis off.
+    pub minimal: bool,
+    /// Skip silence at the start and end of songs.
+    pub skip_silence: bool,
+    /// Seconds to overlap the outgoing and incoming songs when changing
+    /// tracks; no crossfade when unset.
+    pub crossfade_secs: Option<f32>,
+    /// Seconds to fade in a song started on its own, rather than by a
+    /// crossfade; no fade when unset.
+    pub start_fade_secs: Option<f32>,
+    /// Volume in percent to start at, instead of the one saved on quit.
+    pub default_volume: Option<f32>,
+    /// Show a desktop notification when a new song starts.
+    pub notifications: bool,
+    /// Scrobbling is on once the API key, secret and session key are all
+    /// set.
+    #[cfg(feature = "scrobble")]
+    pub lastfm: Option<LastfmCredentials>,
+    /// Apply each track's ReplayGain tag on top of the volume.
+    pub normalize: bool,
+    /// Ask before quitting while a song is playing.
+    pub confirm_quit: bool,
+    /// A preset picked with `theme`, with any individual colors overridden.
+    pub theme: Theme,
+    /// Key bindings, changed per action with `key.<action> = "k, j"`.
+    pub keymap: Keymap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            music_dirs: vec![default_music_dir()],
+            playlist_file: None,
+            play_on_start: None,
+            output_device: None,
+            resume_on_start: true,
+            peak_decay: 1.5,
+            bar_smoothing: 0.5,
+            fft_size: 1024,
+            fft_window: FftWindow::Hanning,
+            buffer_seconds: 5.0,
+            seek_step_secs: 5,
+            minimal: false,
+            skip_silence: false,
+            crossfade_secs: None,
+            start_fade_secs: None,
+            default_volume: None,
+            notifications: true,
+            #[cfg(feature = "scrobble")]
+            lastfm: None,
+            normalize: false,
+            confirm_quit: false,
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+/// The config file as written, before values are checked and applied on
+/// top of the defaults.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    music_dir: Option<String>,
+    music_dirs: Option<Vec<String>>,
+    playlist_file: Option<String>,
+    output_device: Option<String>,
+    resume_on_start: Option<bool>,
+    peak_decay: Option<f32>,
+    bar_smoothing: Option<f32>,
+    fft_size: Option<usize>,
+    fft_window: Option<String>,
+    buffer_seconds: Option<f32>,
+    seek_step_secs: Option<u64>,
+    minimal: Option<bool>,
+    skip_silence: Option<bool>,
+    crossfade_secs: Option<f32>,
+    start_fade_secs: Option<f32>,
+    default_volume: Option<f32>,
+    normalize: Option<bool>,
+    confirm_quit: Option<bool>,
+    notifications: Option<bool>,
+    theme: Option<String>,
+    bar_colors: Option<String>,
+    highlight_fg: Option<String>,
+    highlight_bg: Option<String>,
+    border_color: Option<String>,
+    bar_color: Option<String>,
+    editing_color: Option<String>,
+    playing_color: Option<String>,
+    progress_color: Option<String>,
+    lastfm_api_key: Option<String>,
+    lastfm_api_secret: Option<String>,
+    lastfm_session_key: Option<String>,
+    /// `key.<action> = "k, j"` lines, keyed by action name.
+    #[serde(default)]
+    key: BTreeMap<String, String>,
+}
+
+impl Config {
+    fn parse(contents: &str, path: &Path) -> Result<Config, Box<dyn Error>> {
+        let file: ConfigFile =
+            toml::from_str(contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut config = Config::default();
+        if let Some(dir) = file.music_dir {
+            config.music_dirs = vec![expand_home(&dir)];
+        }
+        if let Some(dirs) = file.music_dirs {
+            config.music_dirs = dirs.iter().map(|dir| expand_home(dir)).collect();
+        }
+        config.playlist_file = file.playlist_file.map(|p| expand_home(&p));
+        config.output_device = file.output_device;
+        config.resume_on_start = file.resume_on_start.unwrap_or(config.resume_on_start);
+        config.peak_decay = file.peak_decay.unwrap_or(config.peak_decay);
+        config.bar_smoothing = file.bar_smoothing.unwrap_or(config.bar_smoothing);
+        if let Some(size) = file.fft_size {
+            if !size.is_power_of_two() || !(MIN_FFT_SIZE..=MAX_FFT_SIZE).contains(&size) {
+                return Err(format!(
+                    "{}: `fft_size` must be a power of two from {} to {}, got {}",
+                    path.display(),
+                    MIN_FFT_SIZE,
+                    MAX_FFT_SIZE,
+                    size
+                )
+                .into());
+            }
+            config.fft_size = size;
+        }
+        if let Some(window) = &file.fft_window {
+            config.fft_window = parse_value("fft_window", window, path)?;
+        }
+        if let Some(seconds) = file.buffer_seconds {
+            if !(MIN_BUFFER_SECONDS..=MAX_BUFFER_SECONDS).contains(&seconds) {
+                return Err(format!(
+                    "{}: `buffer_seconds` must be from {} to {}, got {}",
+                    path.display(),
+                    MIN_BUFFER_SECONDS,
+                    MAX_BUFFER_SECONDS,
+                    seconds
+                )
+                .into());
+            }
+            config.buffer_seconds = seconds;
+        }
+        config.seek_step_secs = file.seek_step_secs.unwrap_or(config.seek_step_secs);
+        config.minimal = file.minimal.unwrap_or(config.minimal);
+        config.skip_silence = file.skip_silence.unwrap_or(config.skip_silence);
+        config.crossfade_secs = file.crossfade_secs;
+        config.start_fade_secs = file.start_fade_secs;
+        if let Some(percent) = file.default_volume {
+            if !(0.0..=MAX_DEFAULT_VOLUME).contains(&percent) {
+                return Err(format!(
+                    "{}: `default_volume` must be from 0 to {}, got {}",
+                    path.display(),
+                    MAX_DEFAULT_VOLUME,
+                    percent
+                )
+                .into());
+            }
+            config.default_volume = Some(percent);
+        }
+        config.normalize = file.normalize.unwrap_or(config.normalize);
+        config.confirm_quit = file.confirm_quit.unwrap_or(config.confirm_quit);
+        config.notifications = file.notifications.unwrap_or(config.notifications);
+
+        if let Some(name) = &file.theme {
+            config.theme = Theme::preset(name).ok_or_else(|| {
+                format!(
+                    "{}: unknown theme `{}` (expected dark, light or solarized)",
+                    path.display(),
+                    name
+                )
+            })?;
+        }
+        // Individual colors apply on top of the preset
+        let colors = [
+            ("highlight_fg", &file.highlight_fg),
+            ("highlight_bg", &file.highlight_bg),
+            ("border_color", &file.border_color),
+            ("bar_color", &file.bar_color),
+            ("editing_color", &file.editing_color),
+            ("playing_color", &file.playing_color),
+            ("progress_color", &file.progress_color),
+        ];
+        for (key, value) in colors {
+            if let (Some(value), Some(color)) = (value, config.theme.color_mut(key)) {
+                *color = parse_value(key, value, path)?;
+            }
+        }
+        if let Some(bar_colors) = &file.bar_colors {
+            config.theme.bar_colors = parse_value("bar_colors", bar_colors, path)?;
+        }
+
+        for (name, value) in &file.key {
+            let (action, keys) = parse_binding(name, value, path)?;
+            config.keymap.rebind(action, &keys);
+        }
+
+        let lastfm = (file.lastfm_api_key, file.lastfm_api_secret, file.lastfm_session_key);
+        #[cfg(feature = "scrobble")]
+        if let (Some(api_key), Some(api_secret), Some(session_key)) = lastfm {
+            config.lastfm = Some(LastfmCredentials { api_key, api_secret, session_key });
+        }
+        #[cfg(not(feature = "scrobble"))]
+        if lastfm.0.is_some() || lastfm.1.is_some() || lastfm.2.is_some() {
+            return Err(format!(
+                "{}: the `lastfm_*` settings need a build with the `scrobble` feature",
+                path.display()
+            )
+            .into());
+        }
+        Ok(config)
+    }
+}
+
+/// Parses `key.<action> = "a, b"` into the action and its keys.
+fn parse_binding(name: &str, value: &str, path: &Path) -> Result<(Action, Vec<KeyCode>), String> {
+    let key = format!("key.{}", name);
+    let action = Action::from_name(name)
+        .ok_or_else(|| format!("{}: unknown action `{}`", path.display(), name))?;
+    let keys = value
+        .split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(|k| {
+            parse_key(k)
+                .ok_or_else(|| format!("{}: unknown key `{}` for `{}`", path.display(), k, key))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((action, keys))
+}
+
+fn parse_value<T: std::str::FromStr>(key: &str, value: &str, path: &Path) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{}: invalid value `{}` for `{}`", path.display(), value, key))
+}
+
+/// Directory holding the config file and other saved state, if a home
+/// directory is known.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join(CONFIG_DIR_NAME))
+}
+
+/// `$XDG_MUSIC_DIR`, else `~/Music`, so the library doesn't depend on the
+/// directory the player was launched from.
+fn default_music_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_MUSIC_DIR").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join("Music"),
+        None => PathBuf::from(FALLBACK_MUSIC_DIR),
+    }
+}
+
+/// Location of the config file, if a home directory is known.
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_FILE_NAME))
+}
+
+/// Reads the config file, falling back to defaults when it doesn't exist.
+pub fn load_config() -> Result<Config, Box<dyn Error>> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+    match fs::read_to_string(&path) {
+        Ok(contents) => Config::parse(&contents, &path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e).into()),
+    }
+}
+
+/// Expands a leading `~/` to the home directory.
+pub fn expand_home(value: &str) -> PathBuf {
+    match (value.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(value),
+    }
+}
@@ -0,0 +1,183 @@
+// src/decoder.rs
+use rodio::{source::SeekError, Decoder, Source};
+use std::{error::Error, fs::File, io::BufReader, path::Path, time::Duration};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{self, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    units::Time,
+};
+
+/// A song's decoded samples, from whichever decoder could open it.
+pub type SongSource = Box<dyn Source<Item = f32> + Send>;
+
+/// Opens `path` with rodio's decoder, falling back to symphonia directly
+/// for files rodio turns down.
+pub fn open_song_source(path: &Path) -> Result<SongSource, Box<dyn Error>> {
+    let file = BufReader::new(File::open(path)?);
+    match Decoder::new(file) {
+        Ok(decoder) => Ok(Box::new(decoder.convert_samples())),
+        Err(rodio_error) => match SymphoniaSource::open(path) {
+            Ok(source) => Ok(Box::new(source)),
+            // rodio's reason is usually the more familiar one
+            Err(_) => Err(rodio_error.into()),
+        },
+    }
+}
+
+/// Decodes with whatever codecs symphonia is built with, probing the
+/// container instead of trusting the file extension.
+pub struct SymphoniaSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn codecs::Decoder>,
+    track_id: u32,
+    /// Interleaved samples of the current packet, and how far into them
+    /// playback is.
+    samples: Vec<f32>,
+    position: usize,
+    /// Samples still to drop after a seek landed before the target.
+    skip: usize,
+    channels: u16,
+    sample_rate: u32,
+    total_duration: Option<Duration>,
+}
+
+impl SymphoniaSource {
+    pub fn open(path: &Path) -> Result<SymphoniaSource, Box<dyn Error>> {
+        let file = File::open(path)?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+        let options = FormatOptions { enable_gapless: true, ..Default::default() };
+        let probed = symphonia::default::get_probe().format(
+            &hint,
+            stream,
+            &options,
+            &MetadataOptions::default(),
+        )?;
+        let format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or("No audio track")?;
+        let decoder =
+            symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+        let params = &track.codec_params;
+        let total_duration = match (params.time_base, params.n_frames) {
+            (Some(time_base), Some(frames)) => {
+                let time = time_base.calc_time(frames);
+                Some(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+            }
+            _ => None,
+        };
+
+        let mut source = SymphoniaSource {
+            track_id: track.id,
+            format,
+            decoder,
+            samples: Vec::new(),
+            position: 0,
+            skip: 0,
+            channels: 0,
+            sample_rate: 0,
+            total_duration,
+        };
+        // Some codecs only reveal the channel layout once decoding starts,
+        // and rodio needs it up front
+        if !source.decode_next_packet() {
+            return Err("No decodable audio".into());
+        }
+        Ok(source)
+    }
+
+    /// Replaces `samples` with the next packet's, returning false at the
+    /// end of the stream or on an unrecoverable error.
+    fn decode_next_packet(&mut self) -> bool {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => return false,
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A corrupt packet is skipped rather than ending the song
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(_) => return false,
+            };
+            let spec = *decoded.spec();
+            self.channels = spec.channels.count() as u16;
+            self.sample_rate = spec.rate;
+            let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            self.samples.clear();
+            self.samples.extend_from_slice(buffer.samples());
+            self.position = 0;
+            if !self.samples.is_empty() {
+                return true;
+            }
+        }
+    }
+}
+
+impl Iterator for SymphoniaSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        loop {
+            if self.position >= self.samples.len() && !self.decode_next_packet() {
+                return None;
+            }
+            let sample = self.samples[self.position];
+            self.position += 1;
+            if self.skip == 0 {
+                return Some(sample);
+            }
+            self.skip -= 1;
+        }
+    }
+}
+
+impl Source for SymphoniaSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let seeked = self
+            .format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time { time: Time::from(pos), track_id: Some(self.track_id) },
+            )
+            .map_err(|e| SeekError::Other(Box::new(e)))?;
+        self.decoder.reset();
+        self.samples.clear();
+        self.position = 0;
+        // Seeking lands on a packet boundary at or before the target
+        let frames = seeked.required_ts.saturating_sub(seeked.actual_ts);
+        self.skip = frames as usize * self.channels as usize;
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+// src/desktop.rs
+use crate::album_art::base64;
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Clipboard helpers tried in order, each reading the text on stdin.
+const CLIPBOARD_COMMANDS: [&[&str]; 5] = [
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["pbcopy"],
+    &["clip"],
+];
+
+/// Clipboard helpers tried in order, each printing the text on stdout.
+const PASTE_COMMANDS: [&[&str]; 5] = [
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+    &["pbpaste"],
+    &["powershell", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+/// Puts `text` on the system clipboard. Without a clipboard helper the
+/// terminal is asked to do it with an OSC 52 sequence, which most modern
+/// terminals (including over SSH) honor.
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    for command in CLIPBOARD_COMMANDS {
+        if pipe_to(command, text).is_ok() {
+            return Ok(());
+        }
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
+fn pipe_to(command: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} failed", command[0])));
+    }
+    Ok(())
+}
+
+/// Reads the system clipboard's text. Terminals can't be asked for it the
+/// way OSC 52 sets it, so this needs one of the clipboard helpers.
+pub fn paste_from_clipboard() -> io::Result<String> {
+    for command in PASTE_COMMANDS {
+        if let Ok(text) = read_from(command) {
+            return Ok(text);
+        }
+    }
+    Err(io::Error::other("no clipboard tool found (wl-paste, xclip, xsel or pbpaste)"))
+}
+
+fn read_from(command: &[&str]) -> io::Result<String> {
+    let output = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("{} failed", command[0])));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Opens `dir` in the platform's file manager without waiting for it.
+pub fn open_in_file_manager(dir: &Path) -> io::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener)
+        .arg(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
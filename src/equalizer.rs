@@ -0,0 +1,203 @@
+// src/equalizer.rs
+use std::{
+    f32::consts::{PI, SQRT_2},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+/// How far each band can be raised or lowered, in dB.
+pub const MAX_GAIN_DB: f32 = 12.0;
+pub const GAIN_STEP_DB: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Band {
+    Bass,
+    Mid,
+    Treble,
+}
+
+impl Band {
+    pub const ALL: [Band; 3] = [Band::Bass, Band::Mid, Band::Treble];
+
+    pub fn next(self) -> Self {
+        match self {
+            Band::Bass => Band::Mid,
+            Band::Mid => Band::Treble,
+            Band::Treble => Band::Bass,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Band::Bass => "Bass",
+            Band::Mid => "Mid",
+            Band::Treble => "Treble",
+        }
+    }
+
+    /// Center or corner frequency in Hz.
+    fn frequency(self) -> f32 {
+        match self {
+            Band::Bass => 150.0,
+            Band::Mid => 1000.0,
+            Band::Treble => 5000.0,
+        }
+    }
+}
+
+/// Band gains in dB, shared between the UI and playback. Stored as f32
+/// bits so playback can read them without locking.
+#[derive(Default)]
+pub struct EqGains([AtomicU32; 3]);
+
+impl EqGains {
+    pub fn get(&self, band: Band) -> f32 {
+        f32::from_bits(self.0[band as usize].load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, band: Band, db: f32) {
+        let db = db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+        self.0[band as usize].store(db.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn all(&self) -> [f32; 3] {
+        Band::ALL.map(|band| self.get(band))
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.all().iter().all(|db| *db == 0.0)
+    }
+}
+
+/// One second-order filter section, with coefficients from the Audio EQ
+/// Cookbook, normalized so `a0` is 1.
+#[derive(Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    /// A low shelf for the bass, a peak for the mids and a high shelf for
+    /// the treble.
+    fn new(band: Band, gain_db: f32, sample_rate: u32) -> Biquad {
+        let a = 10f32.powf(gain_db / 40.0);
+        // Keep the corner below Nyquist for low sample rates
+        let frequency = band.frequency().min(sample_rate as f32 * 0.45);
+        let w0 = 2.0 * PI * frequency / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let (b0, b1, b2, a0, a1, a2) = match band {
+            Band::Mid => {
+                // A Q of 1/√2, about two octaves wide
+                let alpha = sin / SQRT_2;
+                let b1 = -2.0 * cos;
+                (1.0 + alpha * a, b1, 1.0 - alpha * a, 1.0 + alpha / a, b1, 1.0 - alpha / a)
+            }
+            Band::Bass | Band::Treble => {
+                // Shelf slope of 1, the steepest without overshoot
+                let beta = 2.0 * a.sqrt() * sin / SQRT_2;
+                let sign = if band == Band::Bass { 1.0 } else { -1.0 };
+                (
+                    a * ((a + 1.0) - sign * (a - 1.0) * cos + beta),
+                    sign * 2.0 * a * ((a - 1.0) - sign * (a + 1.0) * cos),
+                    a * ((a + 1.0) - sign * (a - 1.0) * cos - beta),
+                    (a + 1.0) + sign * (a - 1.0) * cos + beta,
+                    -sign * 2.0 * ((a - 1.0) + sign * (a + 1.0) * cos),
+                    (a + 1.0) + sign * (a - 1.0) * cos - beta,
+                )
+            }
+        };
+        Biquad { b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: a1 / a0, a2: a2 / a0 }
+    }
+
+    /// Filters one sample; `state` holds the last two inputs and outputs.
+    fn process(&self, x: f32, state: &mut [f32; 4]) -> f32 {
+        let [x1, x2, y1, y2] = *state;
+        let y = self.b0 * x + self.b1 * x1 + self.b2 * x2 - self.a1 * y1 - self.a2 * y2;
+        *state = [x, x1, y, y1];
+        y
+    }
+
+    /// The gain this section applies at `frequency`, in dB.
+    fn response_db(&self, frequency: f32, sample_rate: u32) -> f32 {
+        let w = 2.0 * PI * frequency / sample_rate as f32;
+        let (sin1, cos1) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+        let num_re = self.b0 + self.b1 * cos1 + self.b2 * cos2;
+        let num_im = self.b1 * sin1 + self.b2 * sin2;
+        let den_re = 1.0 + self.a1 * cos1 + self.a2 * cos2;
+        let den_im = self.a1 * sin1 + self.a2 * sin2;
+        let power = (num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im);
+        10.0 * power.max(f32::MIN_POSITIVE).log10()
+    }
+}
+
+/// The combined gain of all three bands at `frequency`, in dB, for drawing
+/// the EQ curve.
+pub fn response_db(gains: [f32; 3], frequency: f32, sample_rate: u32) -> f32 {
+    Band::ALL
+        .iter()
+        .zip(gains)
+        .map(|(&band, db)| Biquad::new(band, db, sample_rate).response_db(frequency, sample_rate))
+        .sum()
+}
+
+/// Runs interleaved samples through the three bands, picking up gain
+/// changes at the start of each frame.
+pub struct Equalizer {
+    gains: Arc<EqGains>,
+    applied: [f32; 3],
+    filters: [Biquad; 3],
+    /// Per channel, each band's filter memory.
+    state: Vec<[[f32; 4]; 3]>,
+    sample_rate: u32,
+    /// The channel the next sample belongs to.
+    channel: usize,
+}
+
+impl Equalizer {
+    pub fn new(gains: Arc<EqGains>, channels: u16, sample_rate: u32) -> Equalizer {
+        let applied = gains.all();
+        Equalizer {
+            filters: filters_for(applied, sample_rate),
+            applied,
+            gains,
+            state: vec![[[0.0; 4]; 3]; channels.max(1) as usize],
+            sample_rate,
+            channel: 0,
+        }
+    }
+
+    /// Starts over at the first channel with empty filters, for after a
+    /// seek threw away buffered audio.
+    pub fn reset(&mut self) {
+        self.channel = 0;
+        self.state.iter_mut().for_each(|state| *state = [[0.0; 4]; 3]);
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        if self.channel == 0 {
+            let gains = self.gains.all();
+            if gains != self.applied {
+                self.filters = filters_for(gains, self.sample_rate);
+                self.applied = gains;
+            }
+        }
+        let channels = self.state.len();
+        let state = &mut self.state[self.channel];
+        self.channel = (self.channel + 1) % channels;
+        if self.applied == [0.0; 3] {
+            return sample;
+        }
+        self.filters.iter().zip(state.iter_mut()).fold(sample, |x, (f, s)| f.process(x, s))
+    }
+}
+
+fn filters_for(gains: [f32; 3], sample_rate: u32) -> [Biquad; 3] {
+    std::array::from_fn(|i| Biquad::new(Band::ALL[i], gains[i], sample_rate))
+}
@@ -0,0 +1,40 @@
+// src/favorites.rs
+use crate::config::config_dir;
+use std::{collections::HashSet, fs, io, path::PathBuf};
+
+const FAVORITES_FILE_NAME: &str = "favorites";
+
+fn favorites_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(FAVORITES_FILE_NAME))
+}
+
+/// Writes the starred songs, one path per line.
+pub fn save_favorites(favorites: &HashSet<PathBuf>) -> io::Result<()> {
+    let Some(path) = favorites_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut lines: Vec<String> = favorites.iter().map(|p| p.display().to_string()).collect();
+    lines.sort();
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    fs::write(path, contents)
+}
+
+/// Reads the starred songs, or none if the file is missing or unreadable.
+pub fn load_favorites() -> HashSet<PathBuf> {
+    let Some(path) = favorites_path() else {
+        return HashSet::new();
+    };
+    fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
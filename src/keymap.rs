@@ -0,0 +1,271 @@
+// src/keymap.rs
+use crossterm::event::KeyCode;
+use std::collections::HashMap;
+
+/// Something a key can do in normal mode.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    PlaySelected,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    RestartSong,
+    SelectDown,
+    SelectUp,
+    PageDown,
+    PageUp,
+    SelectFirst,
+    SelectLast,
+    JumpToPlaying,
+    SeekBackward,
+    SeekForward,
+    JumpToTime,
+    ToggleRemaining,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    SpeedDown,
+    SpeedUp,
+    MarkLoopStart,
+    MarkLoopEnd,
+    ClearLoop,
+    CycleRepeat,
+    ToggleShuffle,
+    CycleSort,
+    ToggleFolderView,
+    EnterFolder,
+    LeaveFolder,
+    ToggleVisualizer,
+    ToggleMinimal,
+    EqNextBand,
+    EqUp,
+    EqDown,
+    Search,
+    ClearSearch,
+    ClearSearchText,
+    RestoreSearch,
+    CycleSearchScope,
+    ToggleFavorite,
+    FavoritesOnly,
+    RemoveSelected,
+    Enqueue,
+    ClearQueue,
+    ExportPlaylist,
+    ToggleRecording,
+    CopyPath,
+    OpenFolder,
+    ChooseDevice,
+    RecentDirs,
+    AddPath,
+    SleepTimer,
+    CommandMode,
+    Help,
+    Quit,
+}
+
+/// Config names for each action, as in `key.play_pause = "p"`.
+const ACTION_NAMES: [(Action, &str); 57] = [
+    (Action::PlaySelected, "play_selected"),
+    (Action::PlayPause, "play_pause"),
+    (Action::Stop, "stop"),
+    (Action::Next, "next"),
+    (Action::Previous, "previous"),
+    (Action::RestartSong, "restart_song"),
+    (Action::SelectDown, "select_down"),
+    (Action::SelectUp, "select_up"),
+    (Action::PageDown, "page_down"),
+    (Action::PageUp, "page_up"),
+    (Action::SelectFirst, "select_first"),
+    (Action::SelectLast, "select_last"),
+    (Action::JumpToPlaying, "jump_to_playing"),
+    (Action::SeekBackward, "seek_backward"),
+    (Action::SeekForward, "seek_forward"),
+    (Action::JumpToTime, "jump_to_time"),
+    (Action::ToggleRemaining, "toggle_remaining"),
+    (Action::VolumeUp, "volume_up"),
+    (Action::VolumeDown, "volume_down"),
+    (Action::ToggleMute, "toggle_mute"),
+    (Action::SpeedDown, "speed_down"),
+    (Action::SpeedUp, "speed_up"),
+    (Action::MarkLoopStart, "mark_loop_start"),
+    (Action::MarkLoopEnd, "mark_loop_end"),
+    (Action::ClearLoop, "clear_loop"),
+    (Action::CycleRepeat, "cycle_repeat"),
+    (Action::ToggleShuffle, "toggle_shuffle"),
+    (Action::CycleSort, "cycle_sort"),
+    (Action::ToggleFolderView, "toggle_folder_view"),
+    (Action::EnterFolder, "enter_folder"),
+    (Action::LeaveFolder, "leave_folder"),
+    (Action::ToggleVisualizer, "toggle_visualizer"),
+    (Action::ToggleMinimal, "toggle_minimal"),
+    (Action::EqNextBand, "eq_next_band"),
+    (Action::EqUp, "eq_up"),
+    (Action::EqDown, "eq_down"),
+    (Action::Search, "search"),
+    (Action::ClearSearch, "clear_search"),
+    (Action::ClearSearchText, "clear_search_text"),
+    (Action::RestoreSearch, "restore_search"),
+    (Action::CycleSearchScope, "cycle_search_scope"),
+    (Action::ToggleFavorite, "toggle_favorite"),
+    (Action::FavoritesOnly, "favorites_only"),
+    (Action::RemoveSelected, "remove_selected"),
+    (Action::Enqueue, "enqueue"),
+    (Action::ClearQueue, "clear_queue"),
+    (Action::ExportPlaylist, "export_playlist"),
+    (Action::ToggleRecording, "toggle_recording"),
+    (Action::CopyPath, "copy_path"),
+    (Action::OpenFolder, "open_folder"),
+    (Action::ChooseDevice, "choose_device"),
+    (Action::RecentDirs, "recent_dirs"),
+    (Action::AddPath, "add_path"),
+    (Action::SleepTimer, "sleep_timer"),
+    (Action::CommandMode, "command_mode"),
+    (Action::Help, "help"),
+    (Action::Quit, "quit"),
+];
+
+const DEFAULT_BINDINGS: [(KeyCode, Action); 57] = [
+    (KeyCode::Char(' '), Action::PlaySelected),
+    (KeyCode::Char('p'), Action::PlayPause),
+    (KeyCode::Char('x'), Action::Stop),
+    (KeyCode::Char('n'), Action::Next),
+    (KeyCode::Char('b'), Action::Previous),
+    (KeyCode::Char('0'), Action::RestartSong),
+    (KeyCode::Down, Action::SelectDown),
+    (KeyCode::Up, Action::SelectUp),
+    (KeyCode::PageDown, Action::PageDown),
+    (KeyCode::PageUp, Action::PageUp),
+    (KeyCode::Home, Action::SelectFirst),
+    (KeyCode::End, Action::SelectLast),
+    (KeyCode::Char('J'), Action::JumpToPlaying),
+    (KeyCode::Left, Action::SeekBackward),
+    (KeyCode::Right, Action::SeekForward),
+    (KeyCode::Char('g'), Action::JumpToTime),
+    (KeyCode::Char('T'), Action::ToggleRemaining),
+    (KeyCode::Char('+'), Action::VolumeUp),
+    (KeyCode::Char('-'), Action::VolumeDown),
+    (KeyCode::Char('m'), Action::ToggleMute),
+    (KeyCode::Char('['), Action::SpeedDown),
+    (KeyCode::Char(']'), Action::SpeedUp),
+    (KeyCode::Char('{'), Action::MarkLoopStart),
+    (KeyCode::Char('}'), Action::MarkLoopEnd),
+    (KeyCode::Char('l'), Action::ClearLoop),
+    (KeyCode::Char('r'), Action::CycleRepeat),
+    (KeyCode::Char('s'), Action::ToggleShuffle),
+    (KeyCode::Char('o'), Action::CycleSort),
+    (KeyCode::Char('t'), Action::ToggleFolderView),
+    (KeyCode::Enter, Action::EnterFolder),
+    (KeyCode::Backspace, Action::LeaveFolder),
+    (KeyCode::Char('v'), Action::ToggleVisualizer),
+    (KeyCode::Char('M'), Action::ToggleMinimal),
+    (KeyCode::Char('E'), Action::EqNextBand),
+    (KeyCode::Char(')'), Action::EqUp),
+    (KeyCode::Char('('), Action::EqDown),
+    (KeyCode::Char('e'), Action::Search),
+    (KeyCode::Char('c'), Action::ClearSearch),
+    (KeyCode::Esc, Action::ClearSearchText),
+    (KeyCode::Char('C'), Action::RestoreSearch),
+    (KeyCode::Char('S'), Action::CycleSearchScope),
+    (KeyCode::Char('f'), Action::ToggleFavorite),
+    (KeyCode::Char('F'), Action::FavoritesOnly),
+    (KeyCode::Char('d'), Action::RemoveSelected),
+    (KeyCode::Char('u'), Action::Enqueue),
+    (KeyCode::Char('U'), Action::ClearQueue),
+    (KeyCode::Char('w'), Action::ExportPlaylist),
+    (KeyCode::Char('W'), Action::ToggleRecording),
+    (KeyCode::Char('y'), Action::CopyPath),
+    (KeyCode::Char('O'), Action::OpenFolder),
+    (KeyCode::Char('a'), Action::ChooseDevice),
+    (KeyCode::Char('R'), Action::RecentDirs),
+    (KeyCode::Char('i'), Action::AddPath),
+    (KeyCode::Char('z'), Action::SleepTimer),
+    (KeyCode::Char(':'), Action::CommandMode),
+    (KeyCode::Char('?'), Action::Help),
+    (KeyCode::Char('q'), Action::Quit),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTION_NAMES.iter().find(|(_, n)| *n == name).map(|(action, _)| *action)
+    }
+}
+
+/// Which action each key triggers in normal mode.
+#[derive(Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyCode, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap { bindings: DEFAULT_BINDINGS.into_iter().collect() }
+    }
+}
+
+impl Keymap {
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+
+    /// Replaces the keys for `action`. Each key is taken from whatever
+    /// action it was bound to before.
+    pub fn rebind(&mut self, action: Action, keys: &[KeyCode]) {
+        self.bindings.retain(|_, bound| *bound != action);
+        for &key in keys {
+            self.bindings.insert(key, action);
+        }
+    }
+
+    /// Names of the keys bound to `action`, sorted for stable display.
+    pub fn key_names(&self, action: Action) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, bound)| **bound == action)
+            .map(|(key, _)| key_name(*key))
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+/// Parses a key as written in the config: a single character, or a name
+/// like `space`, `comma`, `up` or `f5`.
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let lowercase = name.to_lowercase();
+    let key = match lowercase.as_str() {
+        "space" => KeyCode::Char(' '),
+        "comma" => KeyCode::Char(','),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        _ => KeyCode::F(lowercase.strip_prefix('f')?.parse().ok()?),
+    };
+    Some(key)
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        other => format!("{:?}", other),
+    }
+}
@@ -0,0 +1,218 @@
+// src/library.rs
+use crate::{
+    m3u::load_m3u,
+    metadata::{read_metadata, TrackMetadata},
+};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// File extensions rodio or the symphonia fallback can decode with the
+/// enabled features (flac, vorbis, wav, mp3, AAC on its own, AAC or ALAC in
+/// an MP4 container, and AIFF, CAF and Matroska audio). Matched
+/// case-insensitively.
+pub const SUPPORTED_EXTENSIONS: [&str; 11] =
+    ["mp3", "flac", "wav", "ogg", "m4a", "mp4", "aac", "aiff", "aif", "caf", "mka"];
+
+/// How many discovered songs to collect before handing them to the UI.
+const SONG_BATCH_SIZE: usize = 256;
+
+/// How many files to look at between progress reports.
+const PROGRESS_INTERVAL: usize = 100;
+
+/// Where the library loader finds songs.
+pub enum LibrarySource {
+    /// Folders scanned recursively, or single audio files. Songs reachable
+    /// from more than one are only listed once.
+    Directories(Vec<PathBuf>),
+    PlaylistFile(PathBuf),
+}
+
+/// Progress reported by the background library loader. All songs are sent
+/// first, then their metadata, then `Finished`.
+pub enum LibraryEvent {
+    /// Running total of files looked at so far, audio or not.
+    Scanned(usize),
+    Songs(Vec<PathBuf>),
+    Metadata(PathBuf, TrackMetadata),
+    Warning(String),
+    Finished,
+}
+
+pub fn is_supported_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| SUPPORTED_EXTENSIONS.iter().any(|s| s.eq_ignore_ascii_case(ext)))
+}
+
+/// Scans `source` on a background thread so the UI can start immediately.
+/// The thread exits early once the receiver is dropped.
+pub fn spawn_loader(source: LibrarySource) -> Receiver<LibraryEvent> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut loader = Loader {
+            tx,
+            batch: Vec::new(),
+            songs: Vec::new(),
+            seen: HashSet::new(),
+            visited: HashSet::new(),
+            scanned: 0,
+        };
+        if loader.load(&source).is_some() {
+            let _ = loader.tx.send(LibraryEvent::Finished);
+        }
+    });
+    rx
+}
+
+struct Loader {
+    tx: Sender<LibraryEvent>,
+    batch: Vec<PathBuf>,
+    songs: Vec<PathBuf>,
+    /// Songs already found, so overlapping folders or repeated playlist
+    /// entries don't list a song twice.
+    seen: HashSet<PathBuf>,
+    /// Canonical paths of directories already scanned, so symlink loops and
+    /// nested roots are only walked once.
+    visited: HashSet<PathBuf>,
+    scanned: usize,
+}
+
+impl Loader {
+    /// Returns `None` if the receiver went away and loading should stop.
+    fn load(&mut self, source: &LibrarySource) -> Option<()> {
+        match source {
+            LibrarySource::Directories(dirs) => {
+                // A folder that can't be read is reported and the rest still load
+                for dir in dirs {
+                    self.load_directory(dir)?;
+                }
+            }
+            LibrarySource::PlaylistFile(path) => self.load_playlist_file(path)?,
+        }
+        self.flush_batch()?;
+        self.send(LibraryEvent::Scanned(self.scanned))?;
+
+        for song in std::mem::take(&mut self.songs) {
+            if let Some(metadata) = read_metadata(&song) {
+                self.send(LibraryEvent::Metadata(song, metadata))?;
+            }
+        }
+        Some(())
+    }
+
+    fn load_playlist_file(&mut self, path: &Path) -> Option<()> {
+        let m3u = match load_m3u(path) {
+            Ok(m3u) => m3u,
+            Err(e) => {
+                return self.warn(format!("Failed to read playlist {}: {}", path.display(), e));
+            }
+        };
+        if m3u.skipped > 0 {
+            self.warn(format!("Skipped {} missing entries in {}", m3u.skipped, path.display()))?;
+        }
+
+        for song in m3u.songs {
+            self.count_scanned()?;
+            if is_supported_file(&song) {
+                self.add_song(song)?;
+            }
+        }
+        Some(())
+    }
+
+    fn load_directory(&mut self, dir: &Path) -> Option<()> {
+        let root = match dir.canonicalize() {
+            Ok(root) => root,
+            Err(e) => return self.warn(format!("Failed to read {}: {}", dir.display(), e)),
+        };
+        if root.is_file() {
+            self.count_scanned()?;
+            if !is_supported_file(&root) {
+                return self.warn(format!("Not a supported audio file: {}", dir.display()));
+            }
+            return self.add_song(root);
+        }
+        if !self.visited.insert(root.clone()) {
+            return Some(());
+        }
+        self.scan_dir(&root)
+    }
+
+    /// Collects supported files under `dir`. Entries that can't be read are
+    /// skipped with a warning.
+    fn scan_dir(&mut self, dir: &Path) -> Option<()> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => return self.warn(format!("Skipping directory {}: {}", dir.display(), e)),
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    self.warn(format!("Skipping unreadable entry in {}: {}", dir.display(), e))?;
+                    continue;
+                }
+            };
+            let canonical = match path.canonicalize() {
+                Ok(canonical) => canonical,
+                Err(e) => {
+                    self.warn(format!("Skipping {}: {}", path.display(), e))?;
+                    continue;
+                }
+            };
+
+            if canonical.is_dir() {
+                if self.visited.insert(canonical.clone()) {
+                    self.scan_dir(&canonical)?;
+                }
+            } else if canonical.is_file() {
+                self.count_scanned()?;
+                if is_supported_file(&path) {
+                    self.add_song(canonical)?;
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn count_scanned(&mut self) -> Option<()> {
+        self.scanned += 1;
+        if self.scanned.is_multiple_of(PROGRESS_INTERVAL) {
+            self.send(LibraryEvent::Scanned(self.scanned))?;
+        }
+        Some(())
+    }
+
+    fn add_song(&mut self, song: PathBuf) -> Option<()> {
+        if !self.seen.insert(song.clone()) {
+            return Some(());
+        }
+        self.songs.push(song.clone());
+        self.batch.push(song);
+        if self.batch.len() >= SONG_BATCH_SIZE {
+            self.flush_batch()?;
+        }
+        Some(())
+    }
+
+    fn flush_batch(&mut self) -> Option<()> {
+        if self.batch.is_empty() {
+            return Some(());
+        }
+        let batch = std::mem::take(&mut self.batch);
+        self.send(LibraryEvent::Songs(batch))
+    }
+
+    fn warn(&self, message: String) -> Option<()> {
+        self.send(LibraryEvent::Warning(message))
+    }
+
+    fn send(&self, event: LibraryEvent) -> Option<()> {
+        self.tx.send(event).ok()
+    }
+}
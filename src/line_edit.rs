@@ -0,0 +1,108 @@
+// src/line_edit.rs
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// What a key did to the line being edited.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Changed,
+    Moved,
+    Ignored,
+}
+
+/// Applies a readline-style editing key to `text`, where `cursor` is a
+/// byte offset into it: typing inserts at the cursor, the arrows, Home and
+/// End move it, Ctrl+W deletes the word before it and Ctrl+U clears the
+/// line.
+pub fn apply_key(text: &mut String, cursor: &mut usize, key: KeyEvent) -> Edit {
+    clamp_cursor(text, cursor);
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Char('w') if ctrl => {
+            let start = word_start(text, *cursor);
+            if start == *cursor {
+                return Edit::Ignored;
+            }
+            text.replace_range(start..*cursor, "");
+            *cursor = start;
+            Edit::Changed
+        }
+        KeyCode::Char('u') if ctrl => {
+            if text.is_empty() {
+                return Edit::Ignored;
+            }
+            text.clear();
+            *cursor = 0;
+            Edit::Changed
+        }
+        KeyCode::Char('a') if ctrl => move_to(cursor, 0),
+        KeyCode::Char('e') if ctrl => move_to(cursor, text.len()),
+        KeyCode::Char(_) if ctrl => Edit::Ignored,
+        KeyCode::Char(c) => {
+            text.insert(*cursor, c);
+            *cursor += c.len_utf8();
+            Edit::Changed
+        }
+        KeyCode::Backspace => match previous_char(text, *cursor) {
+            Some(start) => {
+                text.replace_range(start..*cursor, "");
+                *cursor = start;
+                Edit::Changed
+            }
+            None => Edit::Ignored,
+        },
+        KeyCode::Delete => match text[*cursor..].chars().next() {
+            Some(c) => {
+                text.replace_range(*cursor..*cursor + c.len_utf8(), "");
+                Edit::Changed
+            }
+            None => Edit::Ignored,
+        },
+        KeyCode::Left => match previous_char(text, *cursor) {
+            Some(start) => move_to(cursor, start),
+            None => Edit::Ignored,
+        },
+        KeyCode::Right => match text[*cursor..].chars().next() {
+            Some(c) => move_to(cursor, *cursor + c.len_utf8()),
+            None => Edit::Ignored,
+        },
+        KeyCode::Home => move_to(cursor, 0),
+        KeyCode::End => move_to(cursor, text.len()),
+        _ => Edit::Ignored,
+    }
+}
+
+/// Inserts `s` at the cursor, leaving the cursor after it.
+pub fn insert(text: &mut String, cursor: &mut usize, s: &str) {
+    clamp_cursor(text, cursor);
+    text.insert_str(*cursor, s);
+    *cursor += s.len();
+}
+
+/// Keeps a cursor left over from before the text was replaced inside it
+/// and on a char boundary.
+fn clamp_cursor(text: &str, cursor: &mut usize) {
+    *cursor = (*cursor).min(text.len());
+    while !text.is_char_boundary(*cursor) {
+        *cursor -= 1;
+    }
+}
+
+fn move_to(cursor: &mut usize, position: usize) -> Edit {
+    if *cursor == position {
+        return Edit::Ignored;
+    }
+    *cursor = position;
+    Edit::Moved
+}
+
+fn previous_char(text: &str, cursor: usize) -> Option<usize> {
+    text[..cursor].char_indices().next_back().map(|(i, _)| i)
+}
+
+/// Where the word before `cursor` starts, skipping any whitespace between
+/// them like readline's Ctrl+W.
+fn word_start(text: &str, cursor: usize) -> usize {
+    let before = text[..cursor].trim_end();
+    let word = before.split(char::is_whitespace).next_back().unwrap_or_default();
+    before.len() - word.len()
+}
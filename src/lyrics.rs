@@ -0,0 +1,68 @@
+// src/lyrics.rs
+use crate::metadata::read_embedded_lyrics;
+use std::{fs, path::Path, time::Duration};
+
+/// Time-synced lyrics, ordered by when each line starts.
+pub struct Lyrics {
+    pub lines: Vec<(Duration, String)>,
+}
+
+impl Lyrics {
+    /// Parses LRC text, where each line starts with one or more `[mm:ss.xx]`
+    /// timestamps. Other tags are skipped apart from `[offset:ms]`, which
+    /// moves every line earlier (or later when negative). Returns `None`
+    /// when no line is timed, as with plain unsynced lyrics.
+    pub fn parse(text: &str) -> Option<Lyrics> {
+        let mut lines = Vec::new();
+        let mut offset_ms: i64 = 0;
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut times = Vec::new();
+            while let Some((tag, after)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                if let Some(time) = parse_timestamp(tag) {
+                    times.push(time);
+                } else if let Some(ms) = tag.strip_prefix("offset:") {
+                    offset_ms = ms.trim().parse().unwrap_or(0);
+                }
+                rest = after;
+            }
+            let text = rest.trim();
+            lines.extend(times.into_iter().map(|time| (time, text.to_string())));
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        let offset = Duration::from_millis(offset_ms.unsigned_abs());
+        for (time, _) in &mut lines {
+            *time = if offset_ms > 0 { time.saturating_sub(offset) } else { *time + offset };
+        }
+        // A line with several timestamps goes in at each of them
+        lines.sort_by_key(|(time, _)| *time);
+        Some(Lyrics { lines })
+    }
+
+    /// The line being sung at `position`, or `None` before the first one.
+    pub fn current_line(&self, position: Duration) -> Option<usize> {
+        self.lines.partition_point(|(time, _)| *time <= position).checked_sub(1)
+    }
+}
+
+/// Loads the synced lyrics for `song`: the `.lrc` file beside it, or else
+/// lyrics embedded in its tags.
+pub fn load_lyrics(song: &Path) -> Option<Lyrics> {
+    fs::read_to_string(song.with_extension("lrc"))
+        .ok()
+        .and_then(|text| Lyrics::parse(&text))
+        .or_else(|| read_embedded_lyrics(song).and_then(|text| Lyrics::parse(&text)))
+}
+
+/// Parses `mm:ss`, `mm:ss.xx` or `mm:ss:xx`.
+fn parse_timestamp(tag: &str) -> Option<Duration> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().replacen(':', ".", 1).parse().ok()?;
+    if !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    Some(Duration::from_secs(minutes * 60) + Duration::from_secs_f64(seconds))
+}
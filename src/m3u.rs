@@ -0,0 +1,78 @@
+// src/m3u.rs
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Songs read from an m3u/m3u8 playlist, plus how many entries pointed at
+/// files that don't exist.
+pub struct M3uPlaylist {
+    pub songs: Vec<PathBuf>,
+    pub skipped: usize,
+}
+
+pub fn is_m3u(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("m3u") || ext.eq_ignore_ascii_case("m3u8"))
+}
+
+/// Parses a plain or extended (`#EXTM3U`/`#EXTINF`) m3u playlist. Relative
+/// entries are resolved against the playlist's own directory; entries whose
+/// files are missing are skipped and counted.
+pub fn load_m3u(path: &Path) -> io::Result<M3uPlaylist> {
+    // m3u files are often Latin-1 rather than UTF-8, so decode lossily
+    let contents = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut playlist = M3uPlaylist { songs: Vec::new(), skipped: 0 };
+    for line in contents.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        // Blank lines, the #EXTM3U header and #EXTINF/other directives
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = Path::new(line.strip_prefix("file://").unwrap_or(line));
+        let entry = if entry.is_absolute() { entry.to_path_buf() } else { base_dir.join(entry) };
+        match entry.canonicalize() {
+            Ok(song) if song.is_file() => playlist.songs.push(song),
+            _ => playlist.skipped += 1,
+        }
+    }
+    Ok(playlist)
+}
+
+/// One song to write to an extended m3u file.
+pub struct M3uEntry<'a> {
+    pub path: &'a Path,
+    pub title: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+/// Writes an extended m3u playlist with absolute paths. Songs with a title get
+/// an `#EXTINF` line, using -1 as the length when it isn't known.
+pub fn save_m3u(path: &Path, entries: &[M3uEntry]) -> io::Result<()> {
+    let mut contents = String::from("#EXTM3U\n");
+    for entry in entries {
+        if let Some(title) = &entry.title {
+            let secs = entry.duration.map_or(-1, |d| d.as_secs() as i64);
+            let _ = writeln!(contents, "#EXTINF:{},{}", secs, title);
+        }
+        let _ = writeln!(contents, "{}", entry.path.display());
+    }
+    fs::write(path, contents)
+}
+
+/// First of `playlist.m3u`, `playlist-2.m3u`, ... that doesn't exist in `dir`.
+pub fn unused_playlist_path(dir: &Path) -> PathBuf {
+    let mut path = dir.join("playlist.m3u");
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("playlist-{}.m3u", n));
+        n += 1;
+    }
+    path
+}
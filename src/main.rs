@@ -1,26 +1,83 @@
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::prelude::*;
-use std::{error::Error, io};
+use std::{
+    env,
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+};
 
+mod album_art;
 mod app;
+mod browser;
+mod command;
+mod config;
+mod desktop;
+mod equalizer;
+mod decoder;
+mod favorites;
+mod keymap;
+mod library;
+mod line_edit;
+mod lyrics;
+mod m3u;
+mod metadata;
+#[cfg(feature = "mpris")]
+mod mpris;
+#[cfg(unix)]
+mod notify;
+mod output;
+mod play_counts;
+mod recent_dirs;
+mod recorder;
+#[cfg(feature = "scrobble")]
+mod scrobbler;
+mod search;
+mod session;
+mod settings;
+mod silence;
+mod theme;
 mod ui;
 
 use app::{run_app, App};
+use config::{load_config, Config};
+use library::is_supported_file;
+use m3u::is_m3u;
+
+const USAGE: &str = "\
+Usage: music_player [PATH]
+
+PATH can be a music folder to load instead of `music_dirs`, a song to start
+playing (its folder is loaded as the playlist), or an .m3u/.m3u8 playlist.
+
+Options:
+  -h, --help  Print this help";
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let mut config = load_config()?;
+    if let Some(arg) = env::args_os().nth(1) {
+        if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        apply_path_arg(&mut config, PathBuf::from(arg))?;
+    }
+
+    // create app before touching the terminal so startup errors print cleanly
+    let app = App::new(config)?;
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // create app and run it
-    let app = App::new()?;
+    // run app
     let res = run_app(&mut terminal, app);
 
     // restore terminal
@@ -28,13 +85,35 @@ fn main() -> Result<(), Box<dyn Error>> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
     if let Err(err) = res {
-        println!("{:?}", err)
+        eprintln!("{}", err)
     }
 
     Ok(())
 }
+
+/// Points the config at the folder, song or playlist given on the command
+/// line.
+fn apply_path_arg(config: &mut Config, arg: PathBuf) -> Result<(), Box<dyn Error>> {
+    // Canonical, so a song given by relative path matches the scanned one
+    let path =
+        arg.canonicalize().map_err(|e| format!("Can't open {}: {}", arg.display(), e))?;
+    if path.is_dir() {
+        config.music_dirs = vec![path];
+        config.playlist_file = None;
+    } else if is_m3u(&path) {
+        config.playlist_file = Some(path);
+    } else if is_supported_file(&path) {
+        config.music_dirs = vec![path.parent().map_or_else(|| path.clone(), Path::to_path_buf)];
+        config.playlist_file = None;
+        config.play_on_start = Some(path);
+    } else {
+        return Err(format!("Not a folder, song or playlist: {}", arg.display()).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,385 @@
+// src/metadata.rs
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    time::Duration,
+};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey},
+    probe::Hint,
+};
+
+/// Tags read from a track's ID3 block, Vorbis comments or container tags,
+/// plus its length when the container records it.
+#[derive(Clone, Default)]
+pub struct TrackMetadata {
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub duration: Option<Duration>,
+    /// `REPLAYGAIN_TRACK_GAIN` in dB.
+    pub replay_gain: Option<f32>,
+}
+
+impl TrackMetadata {
+    /// "Artist - Title", or just the title when there is no artist. Returns
+    /// `None` without a title so callers can fall back to the filename.
+    pub fn display_name(&self) -> Option<String> {
+        let title = self.title.as_ref()?;
+        Some(match &self.artist {
+            Some(artist) => format!("{} - {}", artist, title),
+            None => title.clone(),
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.artist.is_none()
+            && self.title.is_none()
+            && self.album.is_none()
+            && self.duration.is_none()
+            && self.replay_gain.is_none()
+    }
+
+    fn set_vorbis_comment(&mut self, key: &str, value: &str) {
+        if key.eq_ignore_ascii_case("REPLAYGAIN_TRACK_GAIN") {
+            self.replay_gain = self.replay_gain.or_else(|| parse_gain(value));
+            return;
+        }
+        let slot = if key.eq_ignore_ascii_case("ARTIST") {
+            &mut self.artist
+        } else if key.eq_ignore_ascii_case("TITLE") {
+            &mut self.title
+        } else if key.eq_ignore_ascii_case("ALBUM") {
+            &mut self.album
+        } else {
+            return;
+        };
+        if slot.is_none() && !value.is_empty() {
+            *slot = Some(value.to_string());
+        }
+    }
+}
+
+/// Reads the tags of a supported audio file, or `None` if it has none or
+/// can't be parsed.
+pub fn read_metadata(path: &Path) -> Option<TrackMetadata> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let metadata = match extension.as_str() {
+        "flac" => read_flac_comments(path)?,
+        "ogg" => read_ogg_comments(path)?,
+        "wav" => read_wav_info(path)?,
+        // ID3 in MP3 and raw AAC, and each container's own tags otherwise
+        "mp3" | "aac" | "m4a" | "mp4" | "aiff" | "aif" | "caf" | "mka" => {
+            read_probed_tags(path, &extension)?
+        }
+        _ => return None,
+    };
+    if metadata.is_empty() {
+        None
+    } else {
+        Some(metadata)
+    }
+}
+
+/// Reads tags and length with symphonia, for the formats without a
+/// dedicated reader.
+fn read_probed_tags(path: &Path, extension: &str) -> Option<TrackMetadata> {
+    let file = File::open(path).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(extension);
+    let mut probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+
+    // ID3v2 tags sit in front of the stream, so they usually land in the
+    // probe's metadata rather than the format reader's.
+    let revision = probed
+        .metadata
+        .get()
+        .and_then(|m| m.current().cloned())
+        .or_else(|| probed.format.metadata().current().cloned());
+    let mut metadata = TrackMetadata::default();
+    if let Some(revision) = revision {
+        read_revision(&revision, &mut metadata);
+    }
+
+    let params = &probed.format.default_track()?.codec_params;
+    if let (Some(frames), Some(sample_rate)) = (params.n_frames, params.sample_rate) {
+        metadata.duration = Some(samples_to_duration(frames, sample_rate));
+    }
+    Some(metadata)
+}
+
+fn read_revision(revision: &MetadataRevision, metadata: &mut TrackMetadata) {
+    for tag in revision.tags() {
+        if tag.std_key == Some(StandardTagKey::ReplayGainTrackGain) {
+            let gain = parse_gain(&tag.value.to_string());
+            metadata.replay_gain = metadata.replay_gain.or(gain);
+            continue;
+        }
+        let slot = match tag.std_key {
+            Some(StandardTagKey::Artist) => &mut metadata.artist,
+            Some(StandardTagKey::TrackTitle) => &mut metadata.title,
+            Some(StandardTagKey::Album) => &mut metadata.album,
+            _ => continue,
+        };
+        let value = tag.value.to_string();
+        if slot.is_none() && !value.is_empty() {
+            *slot = Some(value);
+        }
+    }
+}
+
+fn read_flac_comments(path: &Path) -> Option<TrackMetadata> {
+    let reader = claxon::FlacReader::open(path).ok()?;
+    let mut metadata = TrackMetadata::default();
+    for (key, value) in reader.tags() {
+        metadata.set_vorbis_comment(key, value);
+    }
+    let info = reader.streaminfo();
+    metadata.duration = info.samples.map(|n| samples_to_duration(n, info.sample_rate));
+    Some(metadata)
+}
+
+fn read_ogg_comments(path: &Path) -> Option<TrackMetadata> {
+    let file = BufReader::new(File::open(path).ok()?);
+    let reader = lewton::inside_ogg::OggStreamReader::new(file).ok()?;
+    let mut metadata = TrackMetadata::default();
+    for (key, value) in &reader.comment_hdr.comment_list {
+        metadata.set_vorbis_comment(key, value);
+    }
+    Some(metadata)
+}
+
+fn read_wav_info(path: &Path) -> Option<TrackMetadata> {
+    let reader = hound::WavReader::open(path).ok()?;
+    let duration = samples_to_duration(reader.duration() as u64, reader.spec().sample_rate);
+    Some(TrackMetadata { duration: Some(duration), ..Default::default() })
+}
+
+/// Technical details of the playing stream.
+#[derive(Clone)]
+pub struct AudioInfo {
+    pub codec: &'static str,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Only known for lossless formats that record it.
+    pub bit_depth: Option<u32>,
+}
+
+impl AudioInfo {
+    /// Reads what the decoder doesn't report (codec and bit depth) from the
+    /// file itself.
+    pub fn new(path: &Path, sample_rate: u32, channels: u16) -> AudioInfo {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let (codec, bit_depth) = match extension.as_str() {
+            "mp3" => ("MP3", None),
+            "ogg" => ("Vorbis", None),
+            "aac" => ("AAC", None),
+            "m4a" | "mp4" | "aiff" | "aif" | "caf" | "mka" => {
+                probe_codec(path, &extension).unwrap_or(("Unknown", None))
+            }
+            "flac" => (
+                "FLAC",
+                claxon::FlacReader::open(path).ok().map(|r| r.streaminfo().bits_per_sample),
+            ),
+            "wav" => (
+                "WAV",
+                hound::WavReader::open(path).ok().map(|r| r.spec().bits_per_sample as u32),
+            ),
+            _ => ("Unknown", None),
+        };
+        AudioInfo { codec, sample_rate, channels, bit_depth }
+    }
+
+    /// For example "FLAC 44.1 kHz 16-bit stereo".
+    pub fn summary(&self) -> String {
+        let channels = match self.channels {
+            1 => "mono".to_string(),
+            2 => "stereo".to_string(),
+            n => format!("{} ch", n),
+        };
+        let rate = format!("{} kHz", self.sample_rate as f32 / 1000.0);
+        match self.bit_depth {
+            Some(bits) => format!("{} {} {}-bit {}", self.codec, rate, bits, channels),
+            None => format!("{} {} {}", self.codec, rate, channels),
+        }
+    }
+}
+
+/// The codec in a container that can hold several, and its bit depth when
+/// it's lossless.
+fn probe_codec(path: &Path, extension: &str) -> Option<(&'static str, Option<u32>)> {
+    let file = File::open(path).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(extension);
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+    let params = &probed.format.default_track()?.codec_params;
+    let bits = params.bits_per_sample;
+    let codec = match symphonia::default::get_codecs().get_codec(params.codec)?.short_name {
+        "aac" => ("AAC", None),
+        "mp3" => ("MP3", None),
+        "vorbis" => ("Vorbis", None),
+        "alac" => ("ALAC", bits),
+        "flac" => ("FLAC", bits),
+        name if name.starts_with("pcm") => ("PCM", bits),
+        name if name.starts_with("adpcm") => ("ADPCM", None),
+        _ => ("Unknown", None),
+    };
+    Some(codec)
+}
+
+/// An image embedded in a track, still in its original encoding.
+#[derive(Clone)]
+pub struct CoverArt {
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Reads the embedded cover of an MP3 (ID3 APIC) or FLAC (PICTURE block),
+/// preferring the front cover when there are several images.
+pub fn read_cover_art(path: &Path) -> Option<CoverArt> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "mp3" => read_id3_picture(path),
+        "flac" => read_flac_picture(path),
+        _ => None,
+    }
+}
+
+fn read_id3_picture(path: &Path) -> Option<CoverArt> {
+    let revision = read_id3_revision(path)?;
+    let visuals = revision.visuals();
+    let visual = visuals
+        .iter()
+        .find(|v| v.usage == Some(StandardVisualKey::FrontCover))
+        .or_else(|| visuals.first())?;
+    Some(CoverArt { media_type: visual.media_type.clone(), data: visual.data.to_vec() })
+}
+
+/// The ID3 tag of an MP3, for the fields only needed while it plays.
+fn read_id3_revision(path: &Path) -> Option<MetadataRevision> {
+    let file = File::open(path).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("mp3");
+    let mut probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+    probed
+        .metadata
+        .get()
+        .and_then(|m| m.current().cloned())
+        .or_else(|| probed.format.metadata().current().cloned())
+}
+
+/// Walks the FLAC metadata blocks looking for a PICTURE block (type 6).
+/// claxon skips these, so the layout is parsed by hand.
+fn read_flac_picture(path: &Path) -> Option<CoverArt> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).ok()?;
+    if &magic != b"fLaC" {
+        return None;
+    }
+    let mut fallback = None;
+    loop {
+        let mut header = [0u8; 4];
+        file.read_exact(&mut header).ok()?;
+        let is_last = header[0] & 0x80 != 0;
+        let block_type = header[0] & 0x7f;
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let mut block = vec![0u8; length];
+        file.read_exact(&mut block).ok()?;
+        if block_type == 6 {
+            if let Some((picture_type, art)) = parse_flac_picture(&block) {
+                // Picture type 3 is the front cover.
+                if picture_type == 3 {
+                    return Some(art);
+                }
+                fallback.get_or_insert(art);
+            }
+        }
+        if is_last {
+            return fallback;
+        }
+    }
+}
+
+fn parse_flac_picture(block: &[u8]) -> Option<(u32, CoverArt)> {
+    let mut pos = 0;
+    let next_u32 = |pos: &mut usize| -> Option<u32> {
+        let bytes = block.get(*pos..*pos + 4)?;
+        *pos += 4;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?))
+    };
+    let picture_type = next_u32(&mut pos)?;
+    let mime_len = next_u32(&mut pos)? as usize;
+    let media_type = String::from_utf8_lossy(block.get(pos..pos + mime_len)?).into_owned();
+    pos += mime_len;
+    let description_len = next_u32(&mut pos)? as usize;
+    // Skip the description, then width, height, depth and palette size.
+    pos += description_len + 16;
+    let data_len = next_u32(&mut pos)? as usize;
+    let data = block.get(pos..pos + data_len)?.to_vec();
+    Some((picture_type, CoverArt { media_type, data }))
+}
+
+/// Reads the lyrics in a track's tags: an ID3 USLT frame or a `LYRICS` /
+/// `UNSYNCEDLYRICS` Vorbis comment. Kept out of `TrackMetadata` so the
+/// library's cache doesn't hold every song's lyrics.
+pub fn read_embedded_lyrics(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let lyrics = match extension.as_str() {
+        "mp3" => read_id3_revision(path)?
+            .tags()
+            .iter()
+            .find(|tag| tag.std_key == Some(StandardTagKey::Lyrics))
+            .map(|tag| tag.value.to_string()),
+        "flac" => find_lyrics_comment(claxon::FlacReader::open(path).ok()?.tags()),
+        "ogg" => {
+            let file = BufReader::new(File::open(path).ok()?);
+            let reader = lewton::inside_ogg::OggStreamReader::new(file).ok()?;
+            let comments = &reader.comment_hdr.comment_list;
+            find_lyrics_comment(comments.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+        }
+        _ => None,
+    };
+    lyrics.filter(|lyrics| !lyrics.trim().is_empty())
+}
+
+fn find_lyrics_comment<'a>(
+    mut comments: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Option<String> {
+    comments
+        .find(|(key, _)| {
+            key.eq_ignore_ascii_case("LYRICS") || key.eq_ignore_ascii_case("UNSYNCEDLYRICS")
+        })
+        .map(|(_, value)| value.to_string())
+}
+
+/// Parses a gain written like "-6.54 dB".
+fn parse_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let number = value.strip_suffix("dB").or_else(|| value.strip_suffix("db")).unwrap_or(value);
+    number.trim().parse().ok().filter(|gain: &f32| gain.is_finite())
+}
+
+/// Length of `frames` per-channel samples at `sample_rate`.
+fn samples_to_duration(frames: u64, sample_rate: u32) -> Duration {
+    if sample_rate == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64(frames as f64 / sample_rate as f64)
+}
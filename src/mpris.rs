@@ -0,0 +1,413 @@
+// src/mpris.rs
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use zbus::{
+    blocking::{connection, object_server::InterfaceRef},
+    fdo, interface,
+    object_server::SignalEmitter,
+    zvariant::{ObjectPath, Value},
+};
+
+use crate::app::RepeatMode;
+
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.simple_music_player";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// How often the player's state is checked for changes to announce.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Requests from desktop media controls, applied by the main loop.
+pub enum MediaCommand {
+    PlayPause,
+    Play,
+    Pause,
+    Stop,
+    Next,
+    Previous,
+    /// Relative seek in microseconds.
+    Seek(i64),
+    /// Absolute position in microseconds.
+    SetPosition(i64),
+    SetVolume(f64),
+    SetShuffle(bool),
+    SetRepeat(RepeatMode),
+}
+
+/// What the desktop is shown about the player, refreshed by the main loop
+/// every tick.
+#[derive(Clone, Default)]
+pub struct NowPlaying {
+    pub path: Option<PathBuf>,
+    pub playing: bool,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub length: Option<Duration>,
+    pub position: Duration,
+    pub volume: f64,
+    pub rate: f64,
+    pub min_rate: f64,
+    pub max_rate: f64,
+    pub shuffle: bool,
+    pub repeat: Option<RepeatMode>,
+    pub seekable: bool,
+    /// How many seeks there have been and where the last one went, so each
+    /// is announced with `Seeked`.
+    pub seeks: (u64, Duration),
+}
+
+impl NowPlaying {
+    fn playback_status(&self) -> &'static str {
+        match (&self.path, self.playing) {
+            (None, _) => "Stopped",
+            (Some(_), true) => "Playing",
+            (Some(_), false) => "Paused",
+        }
+    }
+
+    fn same_track(&self, other: &NowPlaying) -> bool {
+        self.path == other.path
+            && self.title == other.title
+            && self.artist == other.artist
+            && self.album == other.album
+            && self.length == other.length
+    }
+}
+
+/// The main loop's side of the MPRIS service.
+pub struct MprisHandle {
+    pub commands: Receiver<MediaCommand>,
+    pub now_playing: Arc<Mutex<NowPlaying>>,
+    /// Failures, for the main loop to show in the status bar.
+    errors: Receiver<String>,
+}
+
+impl MprisHandle {
+    /// The latest failure since the last call, if any.
+    pub fn take_error(&self) -> Option<String> {
+        self.errors.try_iter().last()
+    }
+}
+
+/// Registers on the session bus and serves MPRIS. zbus answers calls on
+/// its own thread; another announces changes made by the player itself.
+pub fn spawn() -> zbus::Result<MprisHandle> {
+    let (tx, commands) = mpsc::channel();
+    let now_playing = Arc::new(Mutex::new(NowPlaying::default()));
+    let player = Player { tx, now_playing: now_playing.clone() };
+    let connection = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, Root)?
+        .serve_at(OBJECT_PATH, player)?
+        .build()?;
+    let player = connection.object_server().interface::<_, Player>(OBJECT_PATH)?;
+
+    let (error_tx, errors) = mpsc::channel();
+    let shared = now_playing.clone();
+    thread::spawn(move || {
+        // Keeps the service registered while the thread runs
+        let _connection = connection;
+        let mut announced = shared.lock().unwrap().clone();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = shared.lock().unwrap().clone();
+            if let Err(e) = zbus::block_on(announce_changes(&player, &announced, &current)) {
+                let _ = error_tx.send(format!("MPRIS service stopped: {}", e));
+                return;
+            }
+            announced = current;
+        }
+    });
+    Ok(MprisHandle { commands, now_playing, errors })
+}
+
+/// Emits `PropertiesChanged` for player properties that differ between two
+/// snapshots, and `Seeked` after a seek. Position is left out of the
+/// former, as MPRIS clients extrapolate it.
+async fn announce_changes(
+    player: &InterfaceRef<Player>,
+    old: &NowPlaying,
+    new: &NowPlaying,
+) -> zbus::Result<()> {
+    let emitter = player.signal_emitter();
+    let iface = player.get();
+    if old.playback_status() != new.playback_status() {
+        iface.playback_status_changed(emitter).await?;
+    }
+    if old.repeat != new.repeat {
+        iface.loop_status_changed(emitter).await?;
+    }
+    if old.rate != new.rate {
+        iface.rate_changed(emitter).await?;
+    }
+    if old.shuffle != new.shuffle {
+        iface.shuffle_changed(emitter).await?;
+    }
+    if !old.same_track(new) {
+        iface.metadata_changed(emitter).await?;
+    }
+    if old.volume != new.volume {
+        iface.volume_changed(emitter).await?;
+    }
+    if old.seekable != new.seekable {
+        iface.can_seek_changed(emitter).await?;
+    }
+    if old.seeks.0 != new.seeks.0 {
+        Player::seeked(emitter, new.seeks.1.as_micros() as i64).await?;
+    }
+    Ok(())
+}
+
+/// `org.mpris.MediaPlayer2`, which describes the application.
+struct Root;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn identity(&self) -> &str {
+        "Simple Music Player"
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// `org.mpris.MediaPlayer2.Player`, answered from the latest snapshot of
+/// the player and forwarding requests to the main loop.
+struct Player {
+    tx: Sender<MediaCommand>,
+    now_playing: Arc<Mutex<NowPlaying>>,
+}
+
+impl Player {
+    fn snapshot(&self) -> NowPlaying {
+        self.now_playing.lock().unwrap().clone()
+    }
+
+    fn send(&self, command: MediaCommand) {
+        // The receiver only goes away as the player exits
+        let _ = self.tx.send(command);
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(MediaCommand::Next);
+    }
+
+    fn previous(&self) {
+        self.send(MediaCommand::Previous);
+    }
+
+    fn pause(&self) {
+        self.send(MediaCommand::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(MediaCommand::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.send(MediaCommand::Stop);
+    }
+
+    fn play(&self) {
+        self.send(MediaCommand::Play);
+    }
+
+    fn seek(&self, offset: i64) {
+        self.send(MediaCommand::Seek(offset));
+    }
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, position: i64) {
+        self.send(MediaCommand::SetPosition(position));
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        self.snapshot().playback_status()
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> &str {
+        match self.snapshot().repeat {
+            Some(RepeatMode::One) => "Track",
+            Some(RepeatMode::All) => "Playlist",
+            _ => "None",
+        }
+    }
+
+    #[zbus(property)]
+    fn set_loop_status(&mut self, status: &str) -> fdo::Result<()> {
+        let mode = match status {
+            "None" => RepeatMode::Off,
+            "Track" => RepeatMode::One,
+            "Playlist" => RepeatMode::All,
+            _ => return Err(fdo::Error::InvalidArgs(format!("Unknown loop status {}", status))),
+        };
+        self.send(MediaCommand::SetRepeat(mode));
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.snapshot().rate
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.snapshot().shuffle
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&mut self, shuffle: bool) {
+        self.send(MediaCommand::SetShuffle(shuffle));
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, Value<'static>> {
+        metadata(&self.snapshot())
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.snapshot().volume
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        self.send(MediaCommand::SetVolume(volume));
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.snapshot().position.as_micros() as i64
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn minimum_rate(&self) -> f64 {
+        self.snapshot().min_rate
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn maximum_rate(&self) -> f64 {
+        self.snapshot().max_rate
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.snapshot().seekable
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+fn metadata(now: &NowPlaying) -> HashMap<String, Value<'static>> {
+    let mut entries = HashMap::new();
+    let Some(path) = &now.path else {
+        entries.insert("mpris:trackid".to_string(), object_path(NO_TRACK.to_string()));
+        return entries;
+    };
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let track_id = format!("{}/track/{:x}", OBJECT_PATH, hasher.finish());
+    let stem = || path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let title = now.title.clone().unwrap_or_else(stem);
+
+    entries.insert("mpris:trackid".to_string(), object_path(track_id));
+    entries.insert("xesam:title".to_string(), title.into());
+    entries.insert("xesam:url".to_string(), file_url(path).into());
+    if let Some(artist) = &now.artist {
+        entries.insert("xesam:artist".to_string(), vec![artist.clone()].into());
+    }
+    if let Some(album) = &now.album {
+        entries.insert("xesam:album".to_string(), album.clone().into());
+    }
+    if let Some(length) = now.length {
+        entries.insert("mpris:length".to_string(), (length.as_micros() as i64).into());
+    }
+    entries
+}
+
+/// Track ids are built from hex digits, so they're always valid paths.
+fn object_path(path: String) -> Value<'static> {
+    ObjectPath::try_from(path).expect("valid object path").into()
+}
+
+/// A `file://` URL with everything but unreserved characters and `/`
+/// percent-encoded.
+fn file_url(path: &Path) -> String {
+    let mut url = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
@@ -0,0 +1,86 @@
+// src/notify.rs
+use std::{
+    io::{self, Write},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use notify_rust::Notification as DesktopNotification;
+use tempfile::NamedTempFile;
+
+use crate::metadata::CoverArt;
+
+struct Notification {
+    summary: String,
+    body: String,
+    cover: Option<CoverArt>,
+}
+
+/// Sends desktop notifications from a background thread, so a slow
+/// notification daemon never holds up playback.
+pub struct Notifier {
+    tx: Sender<Notification>,
+    /// Failures, for the main loop to show in the status bar.
+    errors: Receiver<String>,
+}
+
+impl Notifier {
+    pub fn spawn() -> Notifier {
+        let (tx, rx) = mpsc::channel::<Notification>();
+        let (error_tx, errors) = mpsc::channel();
+        thread::spawn(move || {
+            // Each new notification replaces the previous one instead of
+            // stacking up
+            let mut replaces_id = None;
+            // Kept until the next notification, as the daemon may read it
+            // after the call returns
+            let mut _shown_cover = None;
+            while let Ok(notification) = rx.recv() {
+                // After several quick track changes only the latest matters
+                let notification = rx.try_iter().last().unwrap_or(notification);
+                let cover = notification.cover.as_ref().and_then(|cover| write_cover(cover).ok());
+                let mut desktop = DesktopNotification::new();
+                desktop
+                    .appname("Simple Music Player")
+                    .summary(&notification.summary)
+                    .body(&notification.body);
+                if let Some(file) = &cover {
+                    desktop.image_path(&file.path().to_string_lossy());
+                }
+                if let Some(id) = replaces_id {
+                    desktop.id(id);
+                }
+                match desktop.show() {
+                    Ok(handle) => replaces_id = Some(handle.id()),
+                    Err(e) => {
+                        let _ = error_tx.send(format!("Failed to show notification: {}", e));
+                    }
+                }
+                _shown_cover = cover;
+            }
+        });
+        Notifier { tx, errors }
+    }
+
+    pub fn notify(&self, summary: String, body: String, cover: Option<CoverArt>) {
+        let _ = self.tx.send(Notification { summary, body, cover });
+    }
+
+    /// The latest failure since the last call, if any.
+    pub fn take_error(&self) -> Option<String> {
+        self.errors.try_iter().last()
+    }
+}
+
+/// Notification daemons take images by path, so the embedded cover is
+/// written to a new private temporary file, deleted once it's dropped.
+fn write_cover(cover: &CoverArt) -> io::Result<NamedTempFile> {
+    let extension = if cover.data.starts_with(b"\x89PNG") { ".png" } else { ".jpg" };
+    let mut file = tempfile::Builder::new()
+        .prefix("simple_music_player-cover-")
+        .suffix(extension)
+        .tempfile()?;
+    file.write_all(&cover.data)?;
+    file.flush()?;
+    Ok(file)
+}
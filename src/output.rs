@@ -0,0 +1,73 @@
+// src/output.rs
+use rodio::{
+    cpal::{self, traits::HostTrait},
+    DeviceTrait, OutputStream, OutputStreamHandle, Sink,
+};
+
+/// An open audio output: the stream must stay alive for the sink to play.
+pub struct AudioOutput {
+    pub stream: OutputStream,
+    /// For opening extra sinks, e.g. to overlap tracks while crossfading.
+    pub handle: OutputStreamHandle,
+    pub sink: Sink,
+    /// Name of the device actually opened, for noticing when it goes away.
+    pub device_name: Option<String>,
+    /// Set when the requested device wasn't found and the default was used.
+    pub warning: Option<String>,
+}
+
+/// Names of the output devices offered by the default host.
+pub fn list_output_devices() -> Vec<String> {
+    cpal::default_host()
+        .output_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Opens the output device whose name matches `device_name` (exactly, or
+/// else as a case-insensitive substring), falling back to the system default
+/// when it isn't given or can't be found.
+pub fn open_output(device_name: Option<&str>) -> Result<AudioOutput, String> {
+    let mut warning = None;
+    let device = match device_name {
+        Some(name) => {
+            let device = find_device(name);
+            if device.is_none() {
+                warning = Some(format!("Output device '{}' not found, using default", name));
+            }
+            device
+        }
+        None => None,
+    };
+
+    let device_name = match &device {
+        Some(device) => device.name().ok(),
+        None => cpal::default_host().default_output_device().and_then(|d| d.name().ok()),
+    };
+    let (stream, handle) = match device {
+        Some(device) => OutputStream::try_from_device(&device),
+        None => OutputStream::try_default(),
+    }
+    .map_err(|e| format!("No audio output device available: {}", e))?;
+    let sink = Sink::try_new(&handle).map_err(|e| format!("Could not open audio output: {}", e))?;
+    Ok(AudioOutput { stream, handle, sink, device_name, warning })
+}
+
+/// Whether a device with exactly this name is currently connected.
+pub fn device_connected(name: &str) -> bool {
+    list_output_devices().iter().any(|device| device == name)
+}
+
+fn find_device(name: &str) -> Option<cpal::Device> {
+    let devices: Vec<cpal::Device> = cpal::default_host().output_devices().ok()?.collect();
+    let lowercase = name.to_lowercase();
+    let position = devices
+        .iter()
+        .position(|d| d.name().is_ok_and(|n| n == name))
+        .or_else(|| {
+            devices
+                .iter()
+                .position(|d| d.name().is_ok_and(|n| n.to_lowercase().contains(&lowercase)))
+        })?;
+    devices.into_iter().nth(position)
+}
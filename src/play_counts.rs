@@ -0,0 +1,43 @@
+// src/play_counts.rs
+use crate::config::config_dir;
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+const PLAY_COUNTS_FILE_NAME: &str = "play_counts";
+
+fn play_counts_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(PLAY_COUNTS_FILE_NAME))
+}
+
+/// Writes how often each song was played, one `count<TAB>path` per line.
+pub fn save_play_counts(counts: &HashMap<PathBuf, u32>) -> io::Result<()> {
+    let Some(path) = play_counts_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut lines: Vec<String> =
+        counts.iter().map(|(p, count)| format!("{}\t{}", count, p.display())).collect();
+    lines.sort();
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    fs::write(path, contents)
+}
+
+/// Reads the play counts, or none if the file is missing or unreadable.
+pub fn load_play_counts() -> HashMap<PathBuf, u32> {
+    let Some(path) = play_counts_path() else {
+        return HashMap::new();
+    };
+    fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter_map(|line| {
+                    let (count, path) = line.split_once('\t')?;
+                    Some((PathBuf::from(path), count.parse().ok()?))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,51 @@
+// src/recent_dirs.rs
+use crate::config::config_dir;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const RECENT_DIRS_FILE_NAME: &str = "recent_dirs";
+/// How many folders the history keeps.
+const MAX_RECENT_DIRS: usize = 10;
+
+fn recent_dirs_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(RECENT_DIRS_FILE_NAME))
+}
+
+/// Moves `dir` to the front of the history, dropping the oldest entry once
+/// it's full.
+pub fn push_recent_dir(dirs: &mut Vec<PathBuf>, dir: &Path) {
+    dirs.retain(|d| d != dir);
+    dirs.insert(0, dir.to_path_buf());
+    dirs.truncate(MAX_RECENT_DIRS);
+}
+
+/// Writes the loaded folders, most recent first, one path per line.
+pub fn save_recent_dirs(dirs: &[PathBuf]) -> io::Result<()> {
+    let Some(path) = recent_dirs_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents: String = dirs.iter().map(|d| format!("{}\n", d.display())).collect();
+    fs::write(path, contents)
+}
+
+/// Reads the folder history, or none if the file is missing or unreadable.
+pub fn load_recent_dirs() -> Vec<PathBuf> {
+    let Some(path) = recent_dirs_path() else {
+        return Vec::new();
+    };
+    fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(PathBuf::from)
+                .take(MAX_RECENT_DIRS)
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,211 @@
+// src/recorder.rs
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::{
+    fs::File,
+    io::BufWriter,
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+type Writer = WavWriter<BufWriter<File>>;
+
+/// Samples a tap collects before sending them to the writer thread.
+const RECORD_CHUNK: usize = 8192;
+
+enum Message {
+    Start(PathBuf),
+    Samples { channels: u16, sample_rate: u32, samples: Vec<f32> },
+    Finish(Sender<Result<Vec<PathBuf>, String>>),
+}
+
+/// Copies played audio to WAV files while recording is on. The current
+/// song's playback source sends its samples through a `RecordTap` as they
+/// leave the playback buffer, and a background thread writes them, so the
+/// audio callback never waits on the disk. A song whose channels or sample
+/// rate differ from the file so far starts a new file.
+pub struct Recorder {
+    active: Arc<AtomicBool>,
+    tx: Sender<Message>,
+    /// Failures, for the main loop to show in the status bar.
+    errors: Receiver<String>,
+}
+
+/// One song's way into the recording.
+pub struct RecordTap {
+    active: Arc<AtomicBool>,
+    /// Cleared once the song stops being the current one, so a song fading
+    /// out under the next isn't recorded over it.
+    current: Arc<AtomicBool>,
+    tx: Sender<Message>,
+    channels: u16,
+    sample_rate: u32,
+    /// Samples not yet sent to the writer thread.
+    pending: Vec<f32>,
+}
+
+struct Recording {
+    dir: PathBuf,
+    writer: Option<(Writer, WavSpec)>,
+    /// Every file written so far, the open one last.
+    paths: Vec<PathBuf>,
+}
+
+impl Recorder {
+    pub fn spawn() -> Recorder {
+        let active = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let (error_tx, errors) = mpsc::channel();
+        let writer_active = active.clone();
+        thread::spawn(move || {
+            let mut recording: Option<Recording> = None;
+            while let Ok(message) = rx.recv() {
+                match message {
+                    Message::Start(dir) => {
+                        recording = Some(Recording { dir, writer: None, paths: Vec::new() });
+                    }
+                    Message::Samples { channels, sample_rate, samples } => {
+                        let Some(current) = &mut recording else {
+                            continue;
+                        };
+                        if let Err(e) = current.write(channels, sample_rate, &samples) {
+                            // Whatever was written stays on disk, unfinalized
+                            recording = None;
+                            writer_active.store(false, Ordering::SeqCst);
+                            let _ = error_tx.send(format!("Recording failed: {}", e));
+                        }
+                    }
+                    Message::Finish(reply) => {
+                        let result = match recording.take() {
+                            Some(recording) => recording.finish().map_err(|e| e.to_string()),
+                            None => Ok(Vec::new()),
+                        };
+                        let _ = reply.send(result);
+                    }
+                }
+            }
+        });
+        Recorder { active, tx, errors }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Starts a recording into `dir`. The file is created once the first
+    /// samples arrive, when their format is known.
+    pub fn start(&self, dir: &Path) {
+        let _ = self.tx.send(Message::Start(dir.to_path_buf()));
+        self.active.store(true, Ordering::SeqCst);
+    }
+
+    /// Ends the recording and finalizes its files, returning their paths,
+    /// or None if there was no recording.
+    pub fn finish(&self) -> Option<Result<Vec<PathBuf>, String>> {
+        if !self.active.swap(false, Ordering::SeqCst) {
+            return None;
+        }
+        let (reply, result) = mpsc::channel();
+        self.tx.send(Message::Finish(reply)).ok()?;
+        result.recv().ok()
+    }
+
+    /// A tap for a song with the given format, recording while `current`
+    /// is set.
+    pub fn tap(&self, channels: u16, sample_rate: u32, current: Arc<AtomicBool>) -> RecordTap {
+        RecordTap {
+            active: self.active.clone(),
+            current,
+            tx: self.tx.clone(),
+            channels,
+            sample_rate,
+            pending: Vec::new(),
+        }
+    }
+
+    /// The latest failure since the last call, if any.
+    pub fn take_error(&self) -> Option<String> {
+        self.errors.try_iter().last()
+    }
+}
+
+impl RecordTap {
+    /// Adds a played sample, ignored unless recording this song.
+    pub fn push(&mut self, sample: f32) {
+        if !self.active.load(Ordering::Relaxed) || !self.current.load(Ordering::Relaxed) {
+            // Left over from a recording that has ended
+            self.pending.clear();
+            return;
+        }
+        self.pending.push(sample);
+        if self.pending.len() >= RECORD_CHUNK {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let samples = mem::replace(&mut self.pending, Vec::with_capacity(RECORD_CHUNK));
+        let _ = self.tx.send(Message::Samples {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            samples,
+        });
+    }
+}
+
+impl Drop for RecordTap {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl Recording {
+    fn write(&mut self, channels: u16, sample_rate: u32, samples: &[f32]) -> hound::Result<()> {
+        let spec = WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        if self.writer.as_ref().is_none_or(|(_, current)| *current != spec) {
+            if let Some((writer, _)) = self.writer.take() {
+                writer.finalize()?;
+            }
+            let path = unused_recording_path(&self.dir);
+            self.writer = Some((WavWriter::create(&path, spec)?, spec));
+            self.paths.push(path);
+        }
+        let (writer, _) = self.writer.as_mut().unwrap();
+        for &sample in samples {
+            writer.write_sample(sample)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> hound::Result<Vec<PathBuf>> {
+        if let Some((writer, _)) = self.writer.take() {
+            writer.finalize()?;
+        }
+        Ok(self.paths)
+    }
+}
+
+/// `recording.wav` in `dir`, or the first of `recording-2.wav`,
+/// `recording-3.wav`, ... that doesn't exist yet.
+fn unused_recording_path(dir: &Path) -> PathBuf {
+    let mut path = dir.join("recording.wav");
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("recording-{}.wav", n));
+        n += 1;
+    }
+    path
+}
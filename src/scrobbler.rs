@@ -0,0 +1,245 @@
+// src/scrobbler.rs
+use crate::config::config_dir;
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+use ureq::Agent;
+
+const API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+const QUEUE_FILE_NAME: &str = "scrobble_queue";
+/// How often to retry queued scrobbles while offline.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Set in the config as `lastfm_api_key`, `lastfm_api_secret` and
+/// `lastfm_session_key`.
+#[derive(Clone)]
+pub struct LastfmCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+    pub session_key: String,
+}
+
+#[derive(Clone)]
+pub struct Track {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub duration: Option<Duration>,
+    /// Unix time the track started playing.
+    pub started_at: u64,
+}
+
+enum Request {
+    NowPlaying(Track),
+    Scrobble(Track),
+}
+
+/// How a submission went: kept for retrying only when Last.fm couldn't be
+/// reached.
+enum Outcome {
+    Done,
+    Rejected(String),
+    Offline,
+}
+
+/// Submits to Last.fm from a background thread. Scrobbles that can't be
+/// sent are queued on disk and retried.
+pub struct Scrobbler {
+    tx: Sender<Request>,
+    /// Failures, for the main loop to show in the status bar.
+    errors: Receiver<String>,
+}
+
+impl Scrobbler {
+    pub fn spawn(credentials: LastfmCredentials) -> Scrobbler {
+        let (tx, rx) = mpsc::channel();
+        let (error_tx, errors) = mpsc::channel();
+        thread::spawn(move || {
+            let client = Client {
+                agent: Agent::config_builder()
+                    .timeout_global(Some(HTTP_TIMEOUT))
+                    .http_status_as_error(false)
+                    .build()
+                    .into(),
+                credentials,
+            };
+            let report = |message: String| {
+                let _ = error_tx.send(message);
+            };
+            let mut queue = load_queue();
+            loop {
+                match rx.recv_timeout(RETRY_INTERVAL) {
+                    Ok(Request::NowPlaying(track)) => {
+                        // Only useful right now, so never retried
+                        let params = track_params(&track, "track.updateNowPlaying");
+                        if let Outcome::Rejected(e) = client.submit(params) {
+                            report(format!("Last.fm now playing update rejected: {}", e));
+                        }
+                    }
+                    Ok(Request::Scrobble(track)) => {
+                        queue.push_back(track);
+                        if let Err(e) = save_queue(&queue) {
+                            report(format!("Failed to save scrobble queue: {}", e));
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+                if let Err(e) = client.flush_queue(&mut queue) {
+                    report(e);
+                }
+            }
+        });
+        Scrobbler { tx, errors }
+    }
+
+    pub fn now_playing(&self, track: Track) {
+        let _ = self.tx.send(Request::NowPlaying(track));
+    }
+
+    pub fn scrobble(&self, track: Track) {
+        let _ = self.tx.send(Request::Scrobble(track));
+    }
+
+    /// The latest failure since the last call, if any.
+    pub fn take_error(&self) -> Option<String> {
+        self.errors.try_iter().last()
+    }
+}
+
+struct Client {
+    agent: Agent,
+    credentials: LastfmCredentials,
+}
+
+impl Client {
+    /// Sends queued scrobbles oldest first, stopping at the first one that
+    /// can't get through. Returns the last failure, if any.
+    fn flush_queue(&self, queue: &mut VecDeque<Track>) -> Result<(), String> {
+        let before = queue.len();
+        let mut result = Ok(());
+        while let Some(track) = queue.front() {
+            let params = track_params(track, "track.scrobble");
+            match self.submit(params) {
+                Outcome::Done => {}
+                Outcome::Rejected(e) => result = Err(format!("Last.fm scrobble rejected: {}", e)),
+                Outcome::Offline => break,
+            }
+            queue.pop_front();
+        }
+        if queue.len() != before {
+            if let Err(e) = save_queue(queue) {
+                result = Err(format!("Failed to save scrobble queue: {}", e));
+            }
+        }
+        result
+    }
+
+    /// Signs and posts an API call.
+    fn submit(&self, mut params: Vec<(String, String)>) -> Outcome {
+        let credentials = &self.credentials;
+        params.push(("api_key".to_string(), credentials.api_key.clone()));
+        params.push(("sk".to_string(), credentials.session_key.clone()));
+        // The signature covers every parameter sorted by name, then the secret
+        params.sort();
+        let mut signed: String = params.iter().map(|(k, v)| format!("{}{}", k, v)).collect();
+        signed.push_str(&credentials.api_secret);
+        params.push(("api_sig".to_string(), format!("{:x}", md5::compute(signed))));
+        params.push(("format".to_string(), "json".to_string()));
+
+        match self.post(&params) {
+            Ok((200, response)) if !response.contains("\"error\"") => Outcome::Done,
+            // Errors 11 and 16 mean the service is down for a while
+            Ok((status, response))
+                if status >= 500
+                    || response.contains("\"error\":11")
+                    || response.contains("\"error\":16") =>
+            {
+                Outcome::Offline
+            }
+            Ok((status, response)) => Outcome::Rejected(format!("HTTP {}: {}", status, response)),
+            Err(_) => Outcome::Offline,
+        }
+    }
+
+    /// Posts the parameters as a form, returning the status code and
+    /// response body.
+    fn post(&self, params: &[(String, String)]) -> Result<(u16, String), ureq::Error> {
+        let form = params.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+        let mut response = self.agent.post(API_URL).send_form(form)?;
+        let body = response.body_mut().read_to_string()?;
+        Ok((response.status().as_u16(), body))
+    }
+}
+
+fn track_params(track: &Track, method: &str) -> Vec<(String, String)> {
+    let mut params = vec![
+        ("method".to_string(), method.to_string()),
+        ("artist".to_string(), track.artist.clone()),
+        ("track".to_string(), track.title.clone()),
+    ];
+    if method == "track.scrobble" {
+        params.push(("timestamp".to_string(), track.started_at.to_string()));
+    }
+    if let Some(album) = &track.album {
+        params.push(("album".to_string(), album.clone()));
+    }
+    if let Some(duration) = track.duration {
+        params.push(("duration".to_string(), duration.as_secs().to_string()));
+    }
+    params
+}
+
+fn queue_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(QUEUE_FILE_NAME))
+}
+
+/// Writes unsent scrobbles one per line as tab-separated
+/// `timestamp, artist, title, album, seconds`.
+fn save_queue(queue: &VecDeque<Track>) -> io::Result<()> {
+    let Some(path) = queue_path() else {
+        return Ok(());
+    };
+    let clean = |s: &str| s.replace(['\t', '\n'], " ");
+    let contents: String = queue
+        .iter()
+        .map(|track| {
+            format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                track.started_at,
+                clean(&track.artist),
+                clean(&track.title),
+                clean(track.album.as_deref().unwrap_or_default()),
+                track.duration.map_or(String::new(), |d| d.as_secs().to_string())
+            )
+        })
+        .collect();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, contents)
+}
+
+fn load_queue() -> VecDeque<Track> {
+    let Some(contents) = queue_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return VecDeque::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let started_at = fields.next()?.parse().ok()?;
+            let artist = fields.next()?.to_string();
+            let title = fields.next()?.to_string();
+            let album = fields.next().filter(|a| !a.is_empty()).map(str::to_string);
+            let duration = fields.next().and_then(|d| d.parse().ok()).map(Duration::from_secs);
+            Some(Track { artist, title, album, duration, started_at })
+        })
+        .collect()
+}
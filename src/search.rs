@@ -0,0 +1,62 @@
+// src/search.rs
+
+const MATCH_SCORE: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 12;
+const WORD_START_BONUS: i64 = 10;
+const GAP_PENALTY: i64 = 1;
+
+/// Scores `candidate` against `query` as a case-insensitive fuzzy match, in
+/// the spirit of skim/fzf: every query character must appear in order, with
+/// bonuses for runs of consecutive characters and for matches at the start of
+/// a word. Whitespace in the query is ignored. Returns `None` when the query
+/// isn't a subsequence of the candidate.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let mut query_chars = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    let mut prev_matched = false;
+    let mut gap = 0;
+    let mut started = false;
+
+    for c in candidate.chars() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+        let matched = c.to_lowercase().eq(std::iter::once(wanted));
+        if matched {
+            score += MATCH_SCORE;
+            if prev_matched {
+                score += CONSECUTIVE_BONUS;
+            }
+            if is_word_start(prev, c) {
+                score += WORD_START_BONUS;
+            }
+            score -= gap * GAP_PENALTY;
+            gap = 0;
+            started = true;
+            query_chars.next();
+        } else if started {
+            gap += 1;
+        }
+        prev_matched = matched;
+        prev = Some(c);
+    }
+
+    if query_chars.peek().is_some() {
+        None
+    } else {
+        Some(score)
+    }
+}
+
+fn is_word_start(prev: Option<char>, c: char) -> bool {
+    match prev {
+        None => true,
+        Some(p) => !p.is_alphanumeric() || (p.is_lowercase() && c.is_uppercase()),
+    }
+}
@@ -0,0 +1,93 @@
+// src/session.rs
+use crate::{
+    config::config_dir,
+    m3u::{save_m3u, M3uEntry},
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::PathBuf, time::Duration};
+
+const SESSION_FILE_NAME: &str = "session";
+const SESSION_PLAYLIST_FILE_NAME: &str = "session.m3u";
+
+/// What was playing when the player last quit, and the playlist around it.
+/// Saved as TOML.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub song: Option<PathBuf>,
+    #[serde(rename = "position_ms", with = "millis")]
+    pub position: Duration,
+    /// The folders the saved playlist was scanned from. It's only restored
+    /// when starting with the same folders, and not saved at all when the
+    /// playlist came from a playlist file.
+    pub music_dirs: Vec<PathBuf>,
+    /// Playlist positions in shuffled play order, when shuffle was on.
+    pub shuffle_order: Vec<usize>,
+}
+
+fn session_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(SESSION_FILE_NAME))
+}
+
+fn session_playlist_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(SESSION_PLAYLIST_FILE_NAME))
+}
+
+/// Writes the session file, and the playlist in its current order beside it
+/// when the session has folders to restore it for.
+pub fn save_session(session: &Session, playlist: &[M3uEntry]) -> io::Result<()> {
+    let (Some(path), Some(playlist_path)) = (session_path(), session_playlist_path()) else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = toml::to_string(session).map_err(io::Error::other)?;
+    fs::write(path, contents)?;
+
+    if session.music_dirs.is_empty() {
+        return match fs::remove_file(playlist_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    save_m3u(&playlist_path, playlist)
+}
+
+/// Reads the last session, ignoring it if the file is missing or malformed.
+/// A song that no longer exists on disk isn't resumed.
+pub fn load_session() -> Option<Session> {
+    let contents = fs::read_to_string(session_path()?).ok()?;
+    let mut session: Session = toml::from_str(&contents).ok()?;
+    session.song = session.song.filter(|song| song.is_file());
+    Some(session)
+}
+
+/// The playlist saved with `session`, if it was scanned from `music_dirs`,
+/// to load in its saved order instead of scanning them again.
+pub fn saved_playlist(session: &Session, music_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if session.music_dirs.is_empty() || session.music_dirs != canonical_dirs(music_dirs) {
+        return None;
+    }
+    session_playlist_path().filter(|path| path.is_file())
+}
+
+/// `dirs` as canonical paths where they exist, so the same folders match
+/// however they were written in the config.
+pub fn canonical_dirs(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    dirs.iter().map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.clone())).collect()
+}
+
+/// Stores a duration as whole milliseconds.
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
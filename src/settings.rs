@@ -0,0 +1,69 @@
+// src/settings.rs
+use crate::{app::RepeatMode, config::config_dir};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::PathBuf};
+
+const SETTINGS_FILE_NAME: &str = "settings";
+
+/// Playback controls that carry over between launches, saved as TOML.
+/// Settings missing from the file keep their defaults.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerSettings {
+    /// Volume before muting, so unmuting after a restart restores it.
+    pub volume: f32,
+    pub muted: bool,
+    pub speed: f32,
+    #[serde(rename = "repeat")]
+    pub repeat_mode: RepeatMode,
+    pub shuffle: bool,
+    /// Bass, mid and treble gains in dB.
+    pub eq: [f32; 3],
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        PlayerSettings {
+            volume: 1.0,
+            muted: false,
+            speed: 1.0,
+            repeat_mode: RepeatMode::Off,
+            shuffle: false,
+            eq: [0.0; 3],
+        }
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(SETTINGS_FILE_NAME))
+}
+
+pub fn save_settings(settings: &PlayerSettings) -> io::Result<()> {
+    let Some(path) = settings_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = toml::to_string(settings).map_err(io::Error::other)?;
+    fs::write(path, contents)
+}
+
+/// Reads the saved settings. A missing, unreadable or malformed file gives
+/// the defaults, as do numbers that aren't finite.
+pub fn load_settings() -> PlayerSettings {
+    let defaults = PlayerSettings::default();
+    let Some(contents) = settings_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return defaults;
+    };
+    let Ok(settings) = toml::from_str::<PlayerSettings>(&contents) else {
+        return defaults;
+    };
+    let finite = |value: f32, default: f32| if value.is_finite() { value } else { default };
+    PlayerSettings {
+        volume: finite(settings.volume, defaults.volume),
+        speed: finite(settings.speed, defaults.speed),
+        eq: settings.eq.map(|db| finite(db, 0.0)),
+        ..settings
+    }
+}
@@ -0,0 +1,75 @@
+// src/silence.rs
+use crate::decoder::SongSource;
+use std::{collections::VecDeque, mem, time::Duration};
+
+/// Samples quieter than this (-60 dBFS) count as silence.
+const SILENCE_THRESHOLD: f32 = 0.001;
+/// The longest quiet stretch held back in case it runs to the end of the
+/// song. Anything longer is a pause within the song and gets played.
+const MAX_HELD_SILENCE: Duration = Duration::from_secs(30);
+
+/// Drops a song's leading silence and holds back quiet stretches until
+/// sound follows them, so trailing silence is never played. Works a frame
+/// at a time to keep the channels in step.
+pub struct SilenceSkipper {
+    channels: usize,
+    /// `MAX_HELD_SILENCE` in samples.
+    max_held: usize,
+    /// Still before the first sound.
+    lead_in: bool,
+    /// The quiet stretch so far, plus the frame being read.
+    held: VecDeque<f32>,
+    /// Samples cleared to play: a quiet stretch and the frame that ended it.
+    ready: VecDeque<f32>,
+    /// Samples dropped from the lead-in since this was last taken, so
+    /// playback position can account for them.
+    pub skipped: u64,
+}
+
+impl SilenceSkipper {
+    pub fn new(channels: u16, sample_rate: u32) -> SilenceSkipper {
+        let channels = channels.max(1) as usize;
+        SilenceSkipper {
+            channels,
+            max_held: (MAX_HELD_SILENCE.as_secs_f32() * sample_rate as f32) as usize * channels,
+            lead_in: true,
+            held: VecDeque::new(),
+            ready: VecDeque::new(),
+            skipped: 0,
+        }
+    }
+
+    /// The next sample to play, or `None` once `source` has only silence
+    /// left.
+    pub fn next(&mut self, source: &mut SongSource) -> Option<f32> {
+        loop {
+            if let Some(sample) = self.ready.pop_front() {
+                return Some(sample);
+            }
+            let mut loud = false;
+            for _ in 0..self.channels {
+                // Whatever is still held is trailing silence
+                let sample = source.next()?;
+                loud |= sample.abs() >= SILENCE_THRESHOLD;
+                self.held.push_back(sample);
+            }
+            if loud {
+                self.lead_in = false;
+                self.ready = mem::take(&mut self.held);
+            } else if self.lead_in {
+                self.held.clear();
+                self.skipped += self.channels as u64;
+            } else if self.held.len() >= self.max_held {
+                self.ready = mem::take(&mut self.held);
+            }
+        }
+    }
+
+    /// Forgets what was held before a seek to `target`. Silence right after
+    /// seeking to the start is a lead-in again.
+    pub fn reset(&mut self, target: Duration) {
+        self.held.clear();
+        self.ready.clear();
+        self.lead_in = target.is_zero();
+    }
+}
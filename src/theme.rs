@@ -0,0 +1,114 @@
+// src/theme.rs
+use ratatui::style::Color;
+use std::str::FromStr;
+
+/// Colors used across the UI, chosen with `theme = "..."` in the config and
+/// adjustable per color.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub highlight_fg: Color,
+    pub highlight_bg: Color,
+    pub border: Color,
+    pub bar: Color,
+    /// Input boxes while typing, and key names in the help.
+    pub editing: Color,
+    /// The now-playing marker and status messages.
+    pub playing: Color,
+    pub progress: Color,
+    pub bar_colors: BarColors,
+}
+
+/// How visualizer bars are colored.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BarColors {
+    /// Every bar in the theme's bar color.
+    Solid,
+    /// Green through yellow to red as a bar gets taller.
+    Magnitude,
+    /// A hue sweep from bass to treble.
+    Frequency,
+}
+
+impl FromStr for BarColors {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "solid" => Ok(BarColors::Solid),
+            "magnitude" => Ok(BarColors::Magnitude),
+            "frequency" => Ok(BarColors::Frequency),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            highlight_fg: Color::Reset,
+            highlight_bg: Color::Blue,
+            border: Color::Reset,
+            bar: Color::Reset,
+            editing: Color::Yellow,
+            playing: Color::Green,
+            progress: Color::Blue,
+            bar_colors: BarColors::Solid,
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            highlight_fg: Color::Black,
+            highlight_bg: Color::LightBlue,
+            border: Color::DarkGray,
+            bar: Color::Blue,
+            editing: Color::Magenta,
+            playing: Color::Green,
+            progress: Color::Blue,
+            bar_colors: BarColors::Solid,
+        }
+    }
+
+    pub fn solarized() -> Self {
+        Theme {
+            highlight_fg: Color::Rgb(0xfd, 0xf6, 0xe3),
+            highlight_bg: Color::Rgb(0x26, 0x8b, 0xd2),
+            border: Color::Rgb(0x58, 0x6e, 0x75),
+            bar: Color::Rgb(0x2a, 0xa1, 0x98),
+            editing: Color::Rgb(0xb5, 0x89, 0x00),
+            playing: Color::Rgb(0x85, 0x99, 0x00),
+            progress: Color::Rgb(0x26, 0x8b, 0xd2),
+            bar_colors: BarColors::Solid,
+        }
+    }
+
+    /// A built-in theme by name.
+    pub fn preset(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "solarized" => Some(Theme::solarized()),
+            _ => None,
+        }
+    }
+
+    /// The color a config key like `highlight_bg` refers to.
+    pub fn color_mut(&mut self, key: &str) -> Option<&mut Color> {
+        match key {
+            "highlight_fg" => Some(&mut self.highlight_fg),
+            "highlight_bg" => Some(&mut self.highlight_bg),
+            "border_color" => Some(&mut self.border),
+            "bar_color" => Some(&mut self.bar),
+            "editing_color" => Some(&mut self.editing),
+            "playing_color" => Some(&mut self.playing),
+            "progress_color" => Some(&mut self.progress),
+            _ => None,
+        }
+    }
+}
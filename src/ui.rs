@@ -1,60 +1,547 @@
 // src/ui.rs
-use crate::app::{App, InputMode};
+use crate::{
+    album_art::{can_display, describe},
+    app::{App, ChannelLevel, DevicePicker, InputMode, SortMode, VisualizerMode},
+    browser::{BrowserFocus, FolderBrowser},
+    equalizer::{response_db, Band, MAX_GAIN_DB},
+    keymap::{Action, Keymap},
+    library::SUPPORTED_EXTENSIONS,
+    theme::{BarColors, Theme},
+};
 // --- MODIFIED IMPORT ---
 // Add Bar and BarGroup to the imports
 use ratatui::{
     prelude::*,
-    widgets::{Bar, BarChart, BarGroup, Block, Borders, List, ListItem, ListState, Paragraph},
+    symbols::Marker,
+    widgets::{
+        canvas::{Canvas, Points},
+        Axis, Bar, BarChart, BarGroup, Block, Borders, Chart, Clear, Dataset, Gauge, GraphType,
+        LineGauge, List, ListItem, ListState, Paragraph, Wrap,
+    },
 };
 // --- END MODIFIED IMPORT ---
+use std::{path::PathBuf, time::Duration};
+
+/// The spectrum's display range. A full-scale sine reads 0 dB; quiet
+/// passages of typical music sit around -60 dB.
+const MIN_DISPLAY_DB: f32 = -80.0;
+const MAX_DISPLAY_DB: f32 = 0.0;
+/// Shades in the waterfall's palette, from silence to full scale.
+const WATERFALL_SHADES: usize = 16;
+const EQ_PANEL_HEIGHT: u16 = 7;
+/// Points along the EQ curve; plenty for a braille line at panel widths.
+const EQ_CURVE_POINTS: usize = 120;
+/// The status bar's note bouncing along while playing, one frame per
+/// `TICKS_PER_FRAME` ticks.
+const PLAYING_FRAMES: [&str; 4] = ["♪  ", " ♪ ", "  ♪", " ♪ "];
+const TICKS_PER_FRAME: u64 = 8;
+
+/// Help rows: the actions whose keys are listed, and what they do.
+const HELP: [(&[Action], &str); 48] = [
+    (&[Action::PlaySelected], "Play selected song"),
+    (&[Action::PlayPause], "Play / pause (Ctrl+Space while typing a search)"),
+    (&[Action::Stop], "Stop"),
+    (&[Action::Next], "Next song"),
+    (&[Action::Previous], "Previous song"),
+    (&[Action::RestartSong], "Restart the current song"),
+    (&[Action::SelectUp, Action::SelectDown], "Move selection"),
+    (&[Action::PageUp, Action::PageDown], "Move selection a page"),
+    (&[Action::SelectFirst, Action::SelectLast], "Select first / last song"),
+    (&[Action::JumpToPlaying], "Select the playing song"),
+    (&[Action::SeekBackward, Action::SeekForward], "Seek (hold Shift for 30 seconds)"),
+    (&[Action::JumpToTime], "Jump to time (MM:SS or N%)"),
+    (&[Action::ToggleRemaining], "Show time remaining / total duration"),
+    (&[Action::VolumeUp, Action::VolumeDown], "Volume up / down"),
+    (&[Action::ToggleMute], "Mute / unmute"),
+    (&[Action::SpeedDown, Action::SpeedUp], "Slower / faster (shifts pitch)"),
+    (&[Action::MarkLoopStart, Action::MarkLoopEnd], "Mark A-B loop start / end"),
+    (&[Action::ClearLoop], "Clear A-B loop"),
+    (&[Action::CycleRepeat], "Cycle repeat mode"),
+    (&[Action::ToggleShuffle], "Toggle shuffle"),
+    (&[Action::CycleSort], "Cycle sort order (name / modified / duration / plays)"),
+    (&[Action::ToggleFolderView], "Switch between playlist and folder view"),
+    (&[Action::EnterFolder, Action::LeaveFolder], "Folder view: open / go back"),
+    (&[Action::ToggleVisualizer], "Switch visualizer (spectrum / waveform / waterfall / lyrics)"),
+    (&[Action::ToggleMinimal], "Minimal mode: hide the visualizer and cover"),
+    (&[Action::EqNextBand], "Select EQ band (bass / mid / treble)"),
+    (&[Action::EqDown, Action::EqUp], "Lower / raise the EQ band"),
+    (&[Action::Search], "Search (Enter / Esc to finish, Ctrl+W / Ctrl+U to delete)"),
+    (&[Action::ClearSearch], "Clear search and favorites filter"),
+    (&[Action::ClearSearchText], "Clear search text only"),
+    (&[Action::RestoreSearch], "Restore the last cleared search"),
+    (&[Action::CycleSearchScope], "Search by file name / path / tags (also Tab while typing)"),
+    (&[Action::ToggleFavorite], "Star / unstar selected song"),
+    (&[Action::FavoritesOnly], "Show only favorites"),
+    (&[Action::RemoveSelected], "Remove selected song from playlist"),
+    (&[Action::Enqueue], "Add selected song to the queue"),
+    (&[Action::ClearQueue], "Clear the queue"),
+    (&[Action::ExportPlaylist], "Export playlist to an m3u file"),
+    (&[Action::ToggleRecording], "Start / stop recording what plays to a WAV file"),
+    (&[Action::CopyPath], "Copy selected song's path"),
+    (&[Action::OpenFolder], "Open selected song's folder"),
+    (&[Action::ChooseDevice], "Choose audio output device"),
+    (&[Action::RecentDirs], "Reopen a recently loaded folder"),
+    (&[Action::AddPath], "Add a file or folder to the playlist"),
+    (&[Action::SleepTimer], "Sleep timer (15 / 30 / 45 / 60 min / off)"),
+    (&[Action::CommandMode], "Command line (goto N, sort, repeat, volume…)"),
+    (&[Action::Help], "Show this help"),
+    (&[Action::Quit], "Quit"),
+];
+
+/// Draws the whole screen. `tick` counts the ticks spent playing and
+/// drives the status bar's animation.
+pub fn ui(f: &mut Frame, app: &mut App, tick: u64) {
+    let theme = app.theme;
+    let mut area = f.area();
+    if app.sink.is_none() || app.device_lost {
+        let banner_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(area);
+        let text = if app.device_lost {
+            let device = app.opened_device.as_deref().unwrap_or("Output device");
+            format!(" {} disconnected - paused until it returns", device)
+        } else {
+            let reason = app.audio_error.as_deref().unwrap_or("No audio output available");
+            format!(" {} - playback is disabled", reason)
+        };
+        let banner = Paragraph::new(text)
+            .style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD));
+        f.render_widget(banner, banner_chunks[0]);
+        area = banner_chunks[1];
+    }
 
-pub fn ui(f: &mut Frame, app: &mut App) {
+    // The command line takes the bottom row while it's open
+    let command_height = u16::from(matches!(app.input_mode, InputMode::Command));
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(3)])
-        .split(f.area());
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(command_height),
+        ])
+        .split(area);
     let top_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .constraints(if app.minimal {
+            [Constraint::Percentage(100), Constraint::Percentage(0)]
+        } else {
+            [Constraint::Percentage(50), Constraint::Percentage(50)]
+        })
         .split(main_chunks[0]);
+    // The queue panel only takes space while something is queued
+    let queue_height = if app.queue.is_empty() { 0 } else { app.queue.len().min(5) as u16 + 2 };
     let left_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+        .constraints([
+            Constraint::Percentage(70),
+            Constraint::Length(queue_height),
+            Constraint::Percentage(30),
+        ])
         .split(top_chunks[0]);
 
-    let items_to_display = if app.input.is_empty() {
-        &app.playlist
-    } else {
-        &app.search_results
-    };
+    let items_to_display = app.active_songs();
+    // Mark the playing song separately from the cursor so both stay visible
     let playlist_items: Vec<ListItem> = items_to_display
         .iter()
-        .map(|p| ListItem::new(p.file_name().unwrap_or_default().to_string_lossy()))
+        .map(|p| {
+            let star = if app.favorites.contains(p) { "★ " } else { "  " };
+            let mut name = app.display_name(p);
+            if app.sort_mode == SortMode::PlayCount {
+                let plays = app.play_counts.get(p).copied().unwrap_or(0);
+                name = format!("{:>3}× {}", plays, name);
+            }
+            if app.current_song_path.as_ref() == Some(p) {
+                ListItem::new(format!("♪ {}{}", star, name))
+                    .style(Style::default().fg(theme.playing))
+            } else if app.unplayable.contains(p) {
+                ListItem::new(format!("✗ {}{}", star, name))
+                    .style(Style::default().fg(Color::Red))
+            } else {
+                ListItem::new(format!("  {}{}", star, name))
+            }
+        })
         .collect();
-    let mut playlist_state = ListState::default();
-    playlist_state.select(app.selected_song_index);
-    let playlist = List::new(playlist_items)
-        .block(Block::default().title("Playlist").borders(Borders::ALL))
-        .highlight_symbol("> ")
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD).bg(Color::Blue));
-    f.render_stateful_widget(playlist, left_chunks[0], &mut playlist_state);
+    if app.playlist_state.selected() != app.selected_song_index {
+        app.playlist_state.select(app.selected_song_index);
+    }
+    let position = format!(
+        "{}/{}",
+        app.selected_song_index.map_or(0, |i| i + 1),
+        playlist_items.len()
+    );
+    let playlist_title = if !app.input.is_empty() {
+        format!("Search results ({})", position)
+    } else if app.favorites_only {
+        format!("Favorites ({})", position)
+    } else if app.is_library_loading() {
+        format!("Playlist (Scanning… {} files, {})", app.files_scanned, position)
+    } else {
+        format!("Playlist ({}) [Sort: {}]", position, app.sort_mode.label())
+    };
+    let mut playlist_area = left_chunks[0];
+    if app.playlist.is_empty() && app.search_results.is_empty() && !app.is_library_loading() {
+        render_onboarding(f, app, playlist_area);
+    } else {
+        let mut tracks_focused = true;
+        if let Some(browser) = &mut app.folder_browser {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .split(playlist_area);
+            tracks_focused = browser.focus == BrowserFocus::Tracks;
+            render_folders(f, browser, panes[0], &theme);
+            playlist_area = panes[1];
+        }
+        let playlist_title = match &app.folder_browser {
+            Some(_) => format!("Tracks ({}) [Sort: {}]", position, app.sort_mode.label()),
+            None => playlist_title,
+        };
+        let playlist = List::new(playlist_items)
+            .block(bordered(playlist_title, &theme))
+            .highlight_symbol("> ")
+            .highlight_style(pane_highlight_style(&theme, tracks_focused));
+        f.render_stateful_widget(playlist, playlist_area, &mut app.playlist_state);
+    }
+    app.playlist_area = playlist_area;
+
+    if !app.queue.is_empty() {
+        let queue_items: Vec<ListItem> =
+            app.queue.iter().map(|p| ListItem::new(app.display_name(p))).collect();
+        let queue_title = format!("Queue ({})", app.queue.len());
+        let queue = List::new(queue_items)
+            .block(bordered(queue_title, &theme));
+        f.render_widget(queue, left_chunks[1]);
+    }
+
+    // The text field being typed in, if any, and how many chars precede
+    // its cursor. Without one the terminal cursor stays hidden.
+    let search_title = format!("Search [{}]", app.search_scope.label());
+    let (input, cursor_column) = match app.input_mode {
+        InputMode::Seeking => {
+            let target = match app.parsed_seek_target() {
+                Some(target) => {
+                    let target = app.current_song_duration.map_or(target, |t| target.min(t));
+                    format!(" -> {}", format_duration(target))
+                }
+                None => String::new(),
+            };
+            let input = Paragraph::new(format!("{}{}", app.seek_input, target))
+                .style(Style::default().fg(theme.editing))
+                .block(bordered("Jump to (MM:SS or N%)", &theme));
+            (input, Some(app.seek_input.chars().count()))
+        }
+        InputMode::AddingPath => {
+            let input = Paragraph::new(app.path_input.as_str())
+                .style(Style::default().fg(theme.editing))
+                .block(bordered("Add file or folder (Enter to add, Esc to cancel)", &theme));
+            let before_cursor = app.path_input.get(..app.path_cursor).unwrap_or(&app.path_input);
+            (input, Some(before_cursor.chars().count()))
+        }
+        InputMode::Editing => {
+            let input = Paragraph::new(app.input.as_str())
+                .style(Style::default().fg(theme.editing))
+                .block(bordered(search_title, &theme));
+            let before_cursor = app.input.get(..app.input_cursor).unwrap_or(&app.input);
+            (input, Some(before_cursor.chars().count()))
+        }
+        _ => (Paragraph::new(app.input.as_str()).block(bordered(search_title, &theme)), None),
+    };
+    match cursor_column {
+        Some(column) => {
+            let (position, scroll) = input_cursor(left_chunks[2], column);
+            f.render_widget(input.scroll((0, scroll)), left_chunks[2]);
+            f.set_cursor_position(position);
+        }
+        None => f.render_widget(input, left_chunks[2]),
+    }
+
+    if app.minimal {
+        app.album_art_area = Rect::default();
+    } else {
+        render_visualizer_panel(f, app, top_chunks[1], &theme);
+    }
+
+    let playback_status = if app.is_playing {
+        let frame = (tick / TICKS_PER_FRAME) as usize % PLAYING_FRAMES.len();
+        format!("Playing {}", PLAYING_FRAMES[frame])
+    } else if app.current_song_path.is_some() {
+        "Paused".to_string()
+    } else {
+        "Stopped".to_string()
+    };
+    let current_song = match (&app.current_metadata, &app.current_song_path) {
+        (Some(metadata), Some(path)) => {
+            metadata.display_name().unwrap_or_else(|| app.display_name(path))
+        }
+        (None, Some(path)) => app.display_name(path),
+        _ => "No song".to_string(),
+    };
+    let volume = if app.muted {
+        "Muted".to_string()
+    } else {
+        format!("{:.0}%", app.volume * 100.0)
+    };
+    let mut status_text = format!(
+        "Status: {} {} | Song: {} | Volume: {} | Speed: {:.2}x | Repeat: {} | Shuffle: {}",
+        playback_status,
+        if app.minimal { String::new() } else { vu_meter(&app.channel_levels.lock().unwrap()) },
+        current_song,
+        volume,
+        app.playback_speed,
+        app.repeat_mode.label(),
+        if app.shuffle { "On" } else { "Off" }
+    );
+    if let Some(info) = &app.current_audio_info {
+        status_text.push_str(&format!(" | {}", info.summary()));
+    }
+    if let Some(remaining) = app.sleep_remaining() {
+        status_text.push_str(&format!(" | Sleep: {}", format_duration(remaining)));
+    }
+    if app.recorder.is_recording() {
+        status_text.push_str(" | Recording");
+    }
+    if let Some(start) = app.loop_start {
+        let end = app.loop_end.map_or_else(|| "…".to_string(), format_duration);
+        status_text.push_str(&format!(" | Loop: {}–{}", format_duration(start), end));
+    }
+    let mut status_block = bordered("Playback", &theme);
+    if matches!(app.input_mode, InputMode::ConfirmingQuit) {
+        status_block = status_block.title(
+            Line::from(" Quit? (y/n) ").right_aligned().fg(theme.editing).bold(),
+        );
+    } else if let Some(message) = app.status_message() {
+        status_block = status_block.title(
+            Line::from(format!(" {} ", message)).right_aligned().fg(theme.playing),
+        );
+    }
+    let status_paragraph = Paragraph::new(status_text).block(status_block);
+    f.render_widget(status_paragraph, main_chunks[1]);
+
+    let elapsed = app.elapsed();
+    let (ratio, progress_label) = match app.current_song_duration {
+        Some(total) if !total.is_zero() => (
+            (elapsed.as_secs_f64() / total.as_secs_f64()).clamp(0.0, 1.0),
+            if app.show_remaining {
+                let remaining = total.saturating_sub(elapsed);
+                format!("{} / -{}", format_duration(elapsed), format_duration(remaining))
+            } else {
+                format!("{} / {}", format_duration(elapsed), format_duration(total))
+            },
+        ),
+        _ => (0.0, format_duration(elapsed)),
+    };
+    let progress = Gauge::default()
+        .block(bordered("Progress", &theme))
+        .gauge_style(Style::default().fg(theme.progress))
+        .ratio(ratio)
+        .label(progress_label);
+    f.render_widget(progress, main_chunks[2]);
+    app.progress_area = main_chunks[2];
+
+    if matches!(app.input_mode, InputMode::Command) {
+        let area = main_chunks[3];
+        let command_line = Paragraph::new(format!(":{}", app.command_input))
+            .style(Style::default().fg(theme.editing));
+        let before_cursor =
+            app.command_input.get(..app.command_cursor).unwrap_or(&app.command_input);
+        // One more column for the `:`
+        let column = 1 + before_cursor.chars().count();
+        let scroll = input_scroll(area.width, column);
+        f.render_widget(command_line.scroll((0, scroll as u16)), area);
+        f.set_cursor_position((area.x + (column - scroll) as u16, area.y));
+    }
+
+    if let Some(picker) = &app.device_picker {
+        render_device_picker(f, picker, app.output_device.as_deref(), &theme);
+    }
+    if let Some(selected) = app.recent_dirs_picker {
+        render_recent_dirs(f, &app.recent_dirs, selected, &theme);
+    }
 
-    let input = Paragraph::new(app.input.as_str())
-        .style(match app.input_mode {
-            InputMode::Normal => Style::default(),
-            InputMode::Editing => Style::default().fg(Color::Yellow),
+    if app.show_help {
+        render_help(f, &app.keymap, &theme);
+    }
+}
+
+/// The cover panel. With a graphics-capable terminal the inside is left
+/// blank for the image drawn after the frame; otherwise it says what's there.
+fn render_album_art(f: &mut Frame, app: &mut App, area: Rect, theme: &Theme) {
+    let block = bordered("Cover", theme);
+    app.album_art_area = block.inner(area);
+    let text = match (&app.cover_art, app.image_protocol) {
+        (None, _) => "No album art".to_string(),
+        (Some(art), Some(protocol)) if can_display(protocol, art) => String::new(),
+        (Some(art), _) => format!("{}\n(image not supported\nby this terminal)", describe(art)),
+    };
+    let placeholder = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray))
+        .wrap(Wrap { trim: true })
+        .block(block);
+    f.render_widget(placeholder, area);
+}
+
+/// A small level meter for the RMS of the playing audio, so it's clear
+/// sound is flowing even when the visualizer shows little.
+fn vu_meter(levels: &[ChannelLevel]) -> String {
+    const CELLS: usize = 8;
+    let power = levels.iter().map(|l| l.rms * l.rms).sum::<f32>() / levels.len().max(1) as f32;
+    let filled = (meter_ratio(power.sqrt()) * CELLS as f64).round() as usize;
+    format!("{}{}", "█".repeat(filled), "░".repeat(CELLS - filled))
+}
+
+/// Where a linear amplitude falls on the level meters' dB scale, from 0 to 1.
+fn meter_ratio(amplitude: f32) -> f64 {
+    const FLOOR_DB: f32 = -48.0;
+    let db = 20.0 * amplitude.max(f32::MIN_POSITIVE).log10();
+    f64::from((1.0 - db / FLOOR_DB).clamp(0.0, 1.0))
+}
+
+/// One RMS gauge per channel, labeled with the channel's peak level.
+fn render_channel_meters(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let block = bordered("Levels", theme);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let levels = app.channel_levels.lock().unwrap().clone();
+    let names: &[&str] = if app.current_channels == 1 { &["M"] } else { &["L", "R"] };
+    for (row, name) in names.iter().enumerate() {
+        let rect = Rect { y: inner.y + row as u16, height: 1, ..inner };
+        if rect.y >= inner.bottom() {
+            break;
+        }
+        let level = levels.get(row).copied().unwrap_or_default();
+        let peak_db = 20.0 * level.peak.max(f32::MIN_POSITIVE).log10();
+        let label = if peak_db > -100.0 {
+            format!("{} {:>6.1} dB", name, peak_db)
+        } else {
+            format!("{}      - dB", name)
+        };
+        let gauge = LineGauge::default()
+            .filled_style(Style::default().fg(magnitude_color(meter_ratio(level.rms) as f32)))
+            .ratio(meter_ratio(level.rms))
+            .label(label);
+        f.render_widget(gauge, rect);
+    }
+}
+
+/// The cover, visualizer, EQ curve and level meters on the right.
+fn render_visualizer_panel(f: &mut Frame, app: &mut App, area: Rect, theme: &Theme) {
+    // A roughly square cover panel beside the visualizer; cells are about
+    // twice as tall as they are wide
+    let art_width = (area.height * 2).min(area.width / 2);
+    let right_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(art_width), Constraint::Min(0)])
+        .split(area);
+    render_album_art(f, app, right_chunks[0], theme);
+    // Left and right level meters under the visualizer while a song is loaded
+    let meter_count = if app.current_song_path.is_some() { app.current_channels.min(2) } else { 0 };
+    let meter_height = if meter_count > 0 { meter_count + 2 } else { 0 };
+    // The EQ curve only takes space once a band has been moved off 0 dB
+    let eq_height = if app.eq_gains.is_flat() { 0 } else { EQ_PANEL_HEIGHT };
+    let visualizer_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(eq_height),
+            Constraint::Length(meter_height),
+        ])
+        .split(right_chunks[1]);
+    match app.visualizer_mode {
+        VisualizerMode::Spectrum => render_spectrum(f, app, visualizer_chunks[0]),
+        VisualizerMode::Waveform => render_waveform(f, app, visualizer_chunks[0]),
+        VisualizerMode::Waterfall => render_waterfall(f, app, visualizer_chunks[0]),
+        VisualizerMode::Lyrics => render_lyrics(f, app, visualizer_chunks[0], theme),
+    }
+    if eq_height > 0 {
+        render_equalizer(f, app, visualizer_chunks[1], theme);
+    }
+    if meter_count > 0 {
+        render_channel_meters(f, app, visualizer_chunks[2], theme);
+    }
+}
+
+/// The EQ's combined response from 20 Hz up, on a log frequency scale,
+/// titled with each band's gain and the band the EQ keys adjust.
+fn render_equalizer(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let gains = app.eq_gains.all();
+    let sample_rate = if app.current_sample_rate > 0 { app.current_sample_rate } else { 44100 };
+    let octaves = (f64::from(sample_rate / 2).min(20000.0) / 20.0).log2();
+    let points: Vec<(f64, f64)> = (0..=EQ_CURVE_POINTS)
+        .map(|i| {
+            let octave = octaves * i as f64 / EQ_CURVE_POINTS as f64;
+            let frequency = 20.0 * 2f64.powf(octave);
+            (octave, f64::from(response_db(gains, frequency as f32, sample_rate)))
         })
-        .block(Block::default().title("Search").borders(Borders::ALL));
-    f.render_widget(input, left_chunks[1]);
+        .collect();
+    let zero_line = [(0.0, 0.0), (octaves, 0.0)];
+
+    let mut title = vec![Span::raw("EQ")];
+    for (band, db) in Band::ALL.into_iter().zip(gains) {
+        let label = format!(" {} {:+}", band.label(), db);
+        if band == app.eq_band {
+            title.push(Span::styled(label, Style::default().fg(theme.editing).bold()));
+        } else {
+            title.push(Span::raw(label));
+        }
+    }
+    let datasets = vec![
+        Dataset::default()
+            .graph_type(GraphType::Line)
+            .marker(Marker::Braille)
+            .style(Style::default().fg(Color::DarkGray))
+            .data(&zero_line),
+        Dataset::default()
+            .graph_type(GraphType::Line)
+            .marker(Marker::Braille)
+            .style(Style::default().fg(theme.bar))
+            .data(&points),
+    ];
+    let bound = f64::from(MAX_GAIN_DB);
+    let chart = Chart::new(datasets)
+        .block(bordered(Line::from(title), theme))
+        .x_axis(Axis::default().bounds([0.0, octaves]))
+        .y_axis(Axis::default().bounds([-bound, bound]));
+    f.render_widget(chart, area);
+}
 
-    let spectrogram_data = app.spectrogram_data.lock().unwrap();
+/// First-run guidance shown in place of an empty playlist.
+fn render_onboarding(f: &mut Frame, app: &App, area: Rect) {
+    let add_keys = app.keymap.key_names(Action::AddPath).join("/");
+    let formats: Vec<String> = SUPPORTED_EXTENSIONS.iter().map(|e| e.to_uppercase()).collect();
+    let mut text = vec![
+        Line::from("No songs yet.").style(Style::default().add_modifier(Modifier::BOLD)),
+        Line::from(""),
+        Line::from("Put music files in:"),
+    ];
+    text.extend(app.music_dirs.iter().map(|dir| {
+        Line::from(format!("  {}", dir.display())).style(Style::default().fg(app.theme.editing))
+    }));
+    text.extend([
+        Line::from("and restart the player, or press"),
+        Line::from(format!("  {} to add a file or folder now.", add_keys)),
+        Line::from(""),
+        Line::from(format!("Supported formats: {}", formats.join(", "))),
+    ]);
+    let paragraph = Paragraph::new(text)
+        .wrap(Wrap { trim: false })
+        .block(bordered("Playlist", &app.theme));
+    f.render_widget(paragraph, area);
+}
+
+/// Spectrum bars over a log frequency axis.
+fn render_spectrum(f: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    // One bar per column inside the block's borders
+    let band_count = area.width.saturating_sub(2) as usize;
+    let bands = log_bands(&app.peak_levels, band_count);
     let max_display_height = 500.0; // Max height for the bars
-    let min_db = -100.0; // Minimum decibel value to display
-    let max_db = 100.0;   // Maximum decibel value to display
+    let min_db = MIN_DISPLAY_DB; // Minimum decibel value to display
+    let max_db = MAX_DISPLAY_DB; // Maximum decibel value to display
 
-    // --- MODIFIED PART ---
-    // Create Bars without explicitly setting labels or value styles
-    let bars: Vec<Bar> = spectrogram_data
+    let heights: Vec<u64> = bands
         .iter()
         .map(|&v| {
             // Clamp the value to the display range
@@ -62,10 +549,24 @@ pub fn ui(f: &mut Frame, app: &mut App) {
             // Normalize to 0-1 range
             let normalized_v = (clamped_v - min_db) / (max_db - min_db);
             // Scale to u64 for display
-            let height = (normalized_v * max_display_height) as u64;
+            (normalized_v * max_display_height) as u64
+        })
+        .collect();
+
+    // --- MODIFIED PART ---
+    // Create Bars without explicitly setting labels or value styles
+    let bars: Vec<Bar> = heights
+        .iter()
+        .enumerate()
+        .map(|(i, &height)| {
+            let color = match theme.bar_colors {
+                BarColors::Solid => theme.bar,
+                BarColors::Magnitude => magnitude_color(height as f32 / max_display_height),
+                BarColors::Frequency => hue_color(270.0 * i as f32 / heights.len() as f32),
+            };
             // Create a Bar with only the value.
             // Not calling .label() or .value_style() should use defaults that render nothing.
-            Bar::default().value(height)
+            Bar::default().value(height).style(Style::default().fg(color))
         })
         .collect();
 
@@ -74,22 +575,298 @@ pub fn ui(f: &mut Frame, app: &mut App) {
 
     // Create the BarChart using the BarGroup
     let barchart = BarChart::default()
-        .block(Block::default().title("Visualizer").borders(Borders::ALL))
+        .block(bordered("Visualizer", theme))
+        .bar_style(Style::default().fg(theme.bar))
         .data(bargroup) // Pass the BarGroup
+        // A fixed scale, so bar height follows loudness instead of the
+        // loudest band at the moment
+        .max(max_display_height as u64)
         .bar_width(1)
         .bar_gap(0);
     // --- END MODIFIED PART ---
 
-    f.render_widget(barchart, top_chunks[1]);
+    f.render_widget(barchart, area);
+}
 
-    let playback_status = if app.is_playing { "Playing" } else { "Paused" };
-    let current_song = app.current_song_path
-        .as_ref()
-        .and_then(|p| p.file_name())
-        .and_then(|s| s.to_str())
-        .unwrap_or("No song");
-    let status_text = format!("Status: {} | Song: {}", playback_status, current_song);
-    let status_block = Block::default().title("Playback").borders(Borders::ALL);
-    let status_paragraph = Paragraph::new(status_text).block(status_block);
-    f.render_widget(status_paragraph, main_chunks[1]);
+/// Recent spectra as a scrolling heat map: a half-cell row per analysis
+/// frame with the newest at the bottom, log frequency across.
+fn render_waterfall(f: &mut Frame, app: &App, area: Rect) {
+    let block = bordered("Waterfall", &app.theme);
+    let inner = block.inner(area);
+    let (width, height) = (inner.width as usize, inner.height as usize * 2);
+    // Each canvas shape has one color, so points are grouped by shade
+    let mut shades: Vec<Vec<(f64, f64)>> = vec![Vec::new(); WATERFALL_SHADES];
+    let history = app.spectrogram_history.lock().unwrap();
+    for (row, spectrum) in history.iter().rev().take(height).enumerate() {
+        for (column, db) in log_bands(spectrum, width).into_iter().enumerate() {
+            let level = (db - MIN_DISPLAY_DB) / (MAX_DISPLAY_DB - MIN_DISPLAY_DB);
+            let shade = (level.clamp(0.0, 1.0) * (WATERFALL_SHADES - 1) as f32).round() as usize;
+            // Silence is left as background
+            if shade > 0 {
+                shades[shade].push((column as f64, row as f64));
+            }
+        }
+    }
+    drop(history);
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::HalfBlock)
+        .x_bounds([0.0, width.saturating_sub(1) as f64])
+        .y_bounds([0.0, height.saturating_sub(1) as f64])
+        .paint(|ctx| {
+            for (shade, coords) in shades.iter().enumerate() {
+                let color = heat_color(shade as f32 / (WATERFALL_SHADES - 1) as f32);
+                ctx.draw(&Points { coords, color });
+            }
+        });
+    f.render_widget(canvas, area);
+}
+
+/// Oscilloscope of the samples behind the latest spectrum.
+fn render_waveform(f: &mut Frame, app: &App, area: Rect) {
+    let samples = app.waveform_data.lock().unwrap();
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, &sample)| (i as f64, sample.clamp(-1.0, 1.0) as f64))
+        .collect();
+    let dataset = Dataset::default()
+        .graph_type(GraphType::Line)
+        .marker(Marker::Braille)
+        .style(Style::default().fg(app.theme.bar))
+        .data(&points);
+    let chart = Chart::new(vec![dataset])
+        .block(bordered("Waveform", &app.theme))
+        .x_axis(Axis::default().bounds([0.0, points.len().max(1) as f64]))
+        .y_axis(Axis::default().bounds([-1.0, 1.0]));
+    f.render_widget(chart, area);
+}
+
+/// The lyrics around the line being sung, which is highlighted and kept in
+/// the middle of the panel.
+fn render_lyrics(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
+    let block = bordered("Lyrics", theme);
+    let Some(lyrics) = &app.lyrics else {
+        let placeholder = Paragraph::new("No lyrics")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        f.render_widget(placeholder, area);
+        return;
+    };
+    let current = lyrics.current_line(app.elapsed());
+    let height = block.inner(area).height as usize;
+    let first = current.unwrap_or(0).saturating_sub(height / 2);
+    let lines: Vec<Line> = lyrics
+        .lines
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(height)
+        .map(|(i, (_, text))| {
+            let style = if Some(i) == current {
+                Style::default().fg(theme.playing).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            Line::styled(text.as_str(), style)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines).alignment(Alignment::Center).block(block), area);
+}
+
+fn render_device_picker(
+    f: &mut Frame,
+    picker: &DevicePicker,
+    current: Option<&str>,
+    theme: &Theme,
+) {
+    let items: Vec<ListItem> = picker
+        .devices
+        .iter()
+        .map(|name| {
+            let marker = if Some(name.as_str()) == current { "* " } else { "  " };
+            ListItem::new(format!("{}{}", marker, name))
+        })
+        .collect();
+    let area = centered_rect(f.area(), 60, items.len() as u16 + 2);
+    let mut state = ListState::default();
+    state.select(Some(picker.selected));
+    let list = List::new(items)
+        .block(bordered("Output device (Enter / Esc)", theme))
+        .highlight_style(highlight_style(theme));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_recent_dirs(f: &mut Frame, dirs: &[PathBuf], selected: usize, theme: &Theme) {
+    let items: Vec<ListItem> =
+        dirs.iter().map(|dir| ListItem::new(dir.display().to_string())).collect();
+    let area = centered_rect(f.area(), 70, items.len() as u16 + 2);
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    let list = List::new(items)
+        .block(bordered("Recent folders (Enter open / a add / Del forget / Esc)", theme))
+        .highlight_style(highlight_style(theme));
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn render_help(f: &mut Frame, keymap: &Keymap, theme: &Theme) {
+    let key_style = Style::default().fg(theme.editing);
+    let mut lines: Vec<Line> = HELP
+        .iter()
+        .map(|(actions, description)| {
+            let keys: Vec<String> = actions
+                .iter()
+                .map(|&action| {
+                    let names = keymap.key_names(action);
+                    if names.is_empty() { "-".to_string() } else { names.join(",") }
+                })
+                .collect();
+            Line::from(vec![
+                Span::styled(format!("{:>14}  ", keys.join(" / ")), key_style),
+                Span::raw(*description),
+            ])
+        })
+        .collect();
+    lines.push(Line::from(vec![
+        Span::styled(format!("{:>14}  ", "Any key"), key_style),
+        Span::raw("Close help"),
+    ]));
+    let area = centered_rect(f.area(), 64, lines.len() as u16 + 2);
+    let help = Paragraph::new(lines).block(bordered("Help", theme));
+    f.render_widget(Clear, area);
+    f.render_widget(help, area);
+}
+
+fn bordered<'a>(title: impl Into<Line<'a>>, theme: &Theme) -> Block<'a> {
+    Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border))
+}
+
+fn highlight_style(theme: &Theme) -> Style {
+    Style::default()
+        .add_modifier(Modifier::BOLD)
+        .fg(theme.highlight_fg)
+        .bg(theme.highlight_bg)
+}
+
+/// The full highlight in the pane the selection keys move in, a plain
+/// bold marker in the other.
+fn pane_highlight_style(theme: &Theme, focused: bool) -> Style {
+    if focused {
+        highlight_style(theme)
+    } else {
+        Style::default().add_modifier(Modifier::BOLD)
+    }
+}
+
+/// The folder view's left pane: the current folder's subfolders.
+fn render_folders(f: &mut Frame, browser: &mut FolderBrowser, area: Rect, theme: &Theme) {
+    let items: Vec<ListItem> = browser
+        .entries
+        .iter()
+        .map(|entry| ListItem::new(format!("📁 {}", browser.entry_name(entry))))
+        .collect();
+    let location = match browser.current.strip_prefix(&browser.root) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative.display().to_string(),
+        _ => browser.root.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+    };
+    let list = List::new(items)
+        .block(bordered(format!("Folders: {}", location), theme))
+        .highlight_symbol("> ")
+        .highlight_style(pane_highlight_style(theme, browser.focus == BrowserFocus::Folders));
+    f.render_stateful_widget(list, area, &mut browser.state);
+}
+
+/// Where the terminal cursor goes in a bordered one-line input at `area`
+/// with `column` chars before it, and how far to scroll the text so the
+/// cursor stays in view.
+fn input_cursor(area: Rect, column: usize) -> (Position, u16) {
+    let scroll = input_scroll(area.width.saturating_sub(2), column);
+    (Position::new(area.x + 1 + (column - scroll) as u16, area.y + 1), scroll as u16)
+}
+
+/// How many chars to scroll a one-line input `width` cells wide so the
+/// cursor, `column` chars in, stays in view.
+fn input_scroll(width: u16, column: usize) -> usize {
+    column.saturating_sub(width.max(1) as usize - 1)
+}
+
+/// A `width` x `height` rect centered in `area`, shrunk to fit if needed.
+fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Groups linear FFT bins into `band_count` bands spaced evenly on a log
+/// frequency axis, so bass and treble get proportionate width. Each band takes
+/// the loudest bin it covers. The DC bin is skipped.
+fn log_bands(bins: &[f32], band_count: usize) -> Vec<f32> {
+    if bins.len() < 2 || band_count == 0 {
+        return Vec::new();
+    }
+    let max_bin = bins.len() as f32;
+    (0..band_count)
+        .map(|band| {
+            let lo = max_bin.powf(band as f32 / band_count as f32) as usize;
+            let hi = (max_bin.powf((band + 1) as f32 / band_count as f32) as usize).max(lo + 1);
+            bins[lo.min(bins.len() - 1)..hi.min(bins.len())]
+                .iter()
+                .copied()
+                .fold(f32::NEG_INFINITY, f32::max)
+        })
+        .collect()
+}
+
+/// Green for quiet bars, yellow in the middle and red near the top.
+fn magnitude_color(level: f32) -> Color {
+    if level < 0.5 {
+        Color::Green
+    } else if level < 0.8 {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+/// Dark blue for quiet, through purple and orange, to pale yellow at full
+/// scale.
+fn heat_color(level: f32) -> Color {
+    const STOPS: [(f32, f32, f32); 4] =
+        [(0.0, 0.0, 90.0), (140.0, 0.0, 160.0), (255.0, 120.0, 0.0), (255.0, 255.0, 190.0)];
+    let position = level.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (position as usize).min(STOPS.len() - 2);
+    let t = position - index as f32;
+    let (from, to) = (STOPS[index], STOPS[index + 1]);
+    let mix = |a: f32, b: f32| (a + (b - a) * t) as u8;
+    Color::Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+/// A fully saturated color at `hue` degrees, 0 being red.
+fn hue_color(hue: f32) -> Color {
+    let sector = (hue / 60.0) % 6.0;
+    let x = ((1.0 - (sector % 2.0 - 1.0).abs()) * 255.0) as u8;
+    match sector as u8 {
+        0 => Color::Rgb(255, x, 0),
+        1 => Color::Rgb(x, 255, 0),
+        2 => Color::Rgb(0, 255, x),
+        3 => Color::Rgb(0, x, 255),
+        4 => Color::Rgb(x, 0, 255),
+        _ => Color::Rgb(255, 0, x),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
 }